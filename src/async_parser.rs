@@ -0,0 +1,255 @@
+use ffi;
+use ffi::YamlErrorType;
+use error::YamlError;
+use event::YamlEvent;
+use document::YamlDocument;
+use parser::{YamlLimits, YamlPushParser, YamlPushLoader, YamlPushResult};
+
+use futures::io::AsyncRead;
+use futures::stream::Stream;
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+const READ_SIZE: usize = 8192;
+
+// Parses a YAML stream from an AsyncRead. Input is handed to a push parser as
+// it arrives, so the streams never block on the reader.
+pub struct YamlAsyncParser<R> {
+    reader: R,
+    encoding: ffi::YamlEncoding
+}
+
+impl<R: AsyncRead + Unpin> YamlAsyncParser<R> {
+    pub fn init(reader: R, encoding: ffi::YamlEncoding) -> YamlAsyncParser<R> {
+        YamlAsyncParser {
+            reader: reader,
+            encoding: encoding
+        }
+    }
+
+    pub fn parse(self) -> YamlAsyncEventStream<R> {
+        YamlAsyncEventStream {
+            input: YamlAsyncInput::new(self.reader),
+            parser: YamlPushParser::new(self.encoding)
+        }
+    }
+
+    pub fn load(self) -> YamlAsyncDocumentStream<R> {
+        self.load_with_limits(Default::default())
+    }
+
+    pub fn load_with_limits(self, limits: YamlLimits) -> YamlAsyncDocumentStream<R> {
+        YamlAsyncDocumentStream {
+            input: YamlAsyncInput::new(self.reader),
+            loader: YamlPushLoader::with_limits(self.encoding, limits)
+        }
+    }
+}
+
+fn read_error(err: io::Error) -> YamlError {
+    let mut error = YamlError::new(YamlErrorType::YAML_READER_ERROR, Some("input error".to_string()));
+    error.io_error = Some(err);
+    error
+}
+
+struct YamlAsyncInput<R> {
+    reader: R,
+    buf: Vec<u8>,
+    // set once the stream has ended or failed
+    done: bool
+}
+
+impl<R: AsyncRead + Unpin> YamlAsyncInput<R> {
+    fn new(reader: R) -> YamlAsyncInput<R> {
+        YamlAsyncInput {
+            reader: reader,
+            buf: vec![0; READ_SIZE],
+            done: false
+        }
+    }
+
+    // reads the next chunk, which is empty at the end of the input
+    fn poll_chunk(&mut self, cx: &mut Context) -> Poll<Result<&[u8], YamlError>> {
+        match Pin::new(&mut self.reader).poll_read(cx, &mut self.buf[..]) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Ok(size)) => Poll::Ready(Ok(&self.buf[..size])),
+            Poll::Ready(Err(err)) => {
+                self.done = true;
+                Poll::Ready(Err(read_error(err)))
+            }
+        }
+    }
+}
+
+pub struct YamlAsyncEventStream<R> {
+    input: YamlAsyncInput<R>,
+    parser: YamlPushParser
+}
+
+impl<R: AsyncRead + Unpin> Stream for YamlAsyncEventStream<R> {
+    type Item = Result<YamlEvent, YamlError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Result<YamlEvent, YamlError>>> {
+        let stream = self.get_mut();
+        while !stream.input.done {
+            match stream.parser.next_event() {
+                Ok(YamlPushResult::YamlReady(event)) => return Poll::Ready(Some(Ok(event))),
+                Ok(YamlPushResult::YamlStreamFinished) => stream.input.done = true,
+                Ok(YamlPushResult::YamlNeedMoreInput) => match stream.input.poll_chunk(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Ok(chunk)) => if chunk.is_empty() {
+                        stream.parser.finish();
+                    } else {
+                        stream.parser.feed(chunk);
+                    },
+                    Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e)))
+                },
+                Err(e) => {
+                    stream.input.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
+        Poll::Ready(None)
+    }
+}
+
+pub struct YamlAsyncDocumentStream<R> {
+    input: YamlAsyncInput<R>,
+    loader: YamlPushLoader
+}
+
+impl<R: AsyncRead + Unpin> Stream for YamlAsyncDocumentStream<R> {
+    type Item = Result<Box<YamlDocument>, YamlError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Result<Box<YamlDocument>, YamlError>>> {
+        let stream = self.get_mut();
+        while !stream.input.done {
+            match stream.loader.next_document() {
+                Ok(YamlPushResult::YamlReady(doc)) => return Poll::Ready(Some(Ok(doc))),
+                Ok(YamlPushResult::YamlStreamFinished) => stream.input.done = true,
+                Ok(YamlPushResult::YamlNeedMoreInput) => match stream.input.poll_chunk(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Ok(chunk)) => if chunk.is_empty() {
+                        stream.loader.finish();
+                    } else {
+                        stream.loader.feed(chunk);
+                    },
+                    Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e)))
+                },
+                Err(e) => {
+                    stream.input.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
+        Poll::Ready(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::YamlAsyncParser;
+    use event::YamlEvent;
+    use parser::{YamlParser, YamlByteParser};
+    use constructor::{YamlConstructor, YamlStandardConstructor};
+    use ffi::YamlErrorType;
+    use ffi::YamlEncoding::YamlUtf8Encoding;
+
+    use futures::executor::block_on;
+    use futures::io::AsyncRead;
+    use futures::stream::StreamExt;
+
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    // hands out a few bytes at a time, and is not ready every other time
+    struct ChunkedReader {
+        data: Vec<u8>,
+        pos: usize,
+        ready: bool,
+        fail: bool
+    }
+
+    impl ChunkedReader {
+        fn new(data: &str) -> ChunkedReader {
+            ChunkedReader { data: data.as_bytes().to_vec(), pos: 0, ready: false, fail: false }
+        }
+    }
+
+    impl AsyncRead for ChunkedReader {
+        fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+            self.ready = !self.ready;
+            if !self.ready {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            if self.fail && self.pos == self.data.len() {
+                return Poll::Ready(Err(io::Error::new(io::ErrorKind::ConnectionReset, "reset")));
+            }
+            let size = buf.len().min(3).min(self.data.len() - self.pos);
+            buf[..size].copy_from_slice(&self.data[self.pos..self.pos + size]);
+            self.pos += size;
+            Poll::Ready(Ok(size))
+        }
+    }
+
+    const DATA: &'static str = "a: [1, 2]\nb: &x {c: d}\nc: *x\n---\n- &y é\n- *y\n- 'str'\n...\n";
+
+    #[test]
+    fn test_async_events() {
+        let expected: Vec<YamlEvent> = YamlByteParser::init(DATA.as_bytes(), YamlUtf8Encoding)
+            .parse().map(|res| res.unwrap()).collect();
+
+        let stream = YamlAsyncParser::init(ChunkedReader::new(DATA), YamlUtf8Encoding).parse();
+        let events: Vec<YamlEvent> = block_on(stream.map(|res| res.unwrap()).collect());
+
+        let summary = |events: &[YamlEvent]| events.iter().map(|e| (e.spec.clone(), e.start, e.end)).collect::<Vec<_>>();
+        assert_eq!(summary(&expected), summary(&events));
+    }
+
+    #[test]
+    fn test_async_load() {
+        let ctor = YamlStandardConstructor::new();
+        let expected = ::parse_bytes_utf8(DATA.as_bytes()).unwrap();
+
+        let stream = YamlAsyncParser::init(ChunkedReader::new(DATA), YamlUtf8Encoding).load();
+        let docs: Vec<_> = block_on(stream.map(|res| ctor.construct(res.unwrap().root().unwrap()).unwrap()).collect());
+
+        assert_eq!(expected, docs);
+    }
+
+    #[test]
+    fn test_async_errors() {
+        let stream = YamlAsyncParser::init(ChunkedReader::new("[1, 2"), YamlUtf8Encoding).parse();
+        let results: Vec<_> = block_on(stream.collect());
+        assert_eq!(YamlErrorType::YAML_PARSER_ERROR, results.last().unwrap().as_ref().unwrap_err().kind);
+
+        let mut reader = ChunkedReader::new("- a\n");
+        reader.fail = true;
+        let stream = YamlAsyncParser::init(reader, YamlUtf8Encoding).load();
+        let results: Vec<_> = block_on(stream.collect());
+        assert_eq!(1, results.len());
+        let err = results[0].as_ref().err().unwrap();
+        assert_eq!(YamlErrorType::YAML_READER_ERROR, err.kind);
+        assert_eq!(io::ErrorKind::ConnectionReset, err.io_error.as_ref().unwrap().kind());
+    }
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    #[test]
+    fn test_async_send() {
+        let parser = YamlAsyncParser::init(ChunkedReader::new(DATA), YamlUtf8Encoding);
+        assert_send(&parser);
+        assert_send(&parser.load());
+        assert_send(&YamlAsyncParser::init(ChunkedReader::new(DATA), YamlUtf8Encoding).parse());
+
+        let future = ::parse_io_async(ChunkedReader::new(DATA), YamlUtf8Encoding);
+        assert_send(&future);
+        let expected = ::parse_bytes_utf8(DATA.as_bytes()).unwrap();
+        assert_eq!(expected, block_on(future).unwrap());
+    }
+}
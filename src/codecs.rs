@@ -28,3 +28,87 @@ pub fn decode_buf(buf: *const ffi::yaml_char_t, length: libc::size_t) -> Option<
         }
     }
 }
+
+static BASE64_CHARS: &'static [u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn encode_base64(data: &[u8]) -> String {
+    let mut buf = String::new();
+
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as usize) << 16 | (b[1] as usize) << 8 | b[2] as usize;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                buf.push(BASE64_CHARS[(n >> (18 - 6 * i)) & 0x3f] as char);
+            } else {
+                buf.push('=');
+            }
+        }
+    }
+
+    buf
+}
+
+// whitespace (including line breaks) is ignored, and padding is optional
+pub fn decode_base64(data: &str) -> Option<Vec<u8>> {
+    let mut buf = Vec::new();
+    let mut n: u32 = 0;
+    let mut bits = 0;
+    let mut padding = 0;
+
+    for c in data.bytes() {
+        let value = match c {
+            b'A' ..= b'Z' => c - b'A',
+            b'a' ..= b'z' => c - b'a' + 26,
+            b'0' ..= b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => {
+                padding += 1;
+                continue;
+            },
+            b' ' | b'\t' | b'\r' | b'\n' => continue,
+            _ => return None
+        };
+        if padding > 0 {
+            return None;
+        }
+
+        n = n << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            buf.push((n >> bits) as u8);
+            n &= (1 << bits) - 1;
+        }
+    }
+
+    // a single dangling character can't encode a whole byte
+    if bits >= 6 || padding > 2 {
+        None
+    } else {
+        Some(buf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{encode_base64, decode_base64};
+
+    #[test]
+    fn test_base64_round_trip() {
+        for data in [&b""[..], b"f", b"fo", b"foo", b"foob", b"fooba", b"foobar"].iter() {
+            assert_eq!(Some(data.to_vec()), decode_base64(&encode_base64(data)[..]));
+        }
+        assert_eq!("Zm9vYmE=", encode_base64(b"fooba"));
+    }
+
+    #[test]
+    fn test_base64_whitespace() {
+        assert_eq!(Some(b"foobar".to_vec()), decode_base64("Zm9v\n  YmFy\n"));
+        assert_eq!(Some(b"fo".to_vec()), decode_base64("Zm8"));
+        assert_eq!(None, decode_base64("Zm9v!"));
+        assert_eq!(None, decode_base64("Z"));
+    }
+}
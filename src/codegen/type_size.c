@@ -52,6 +52,70 @@ int main()
     printf("    YAML_MAPPING_END_EVENT\n");
     printf("}\n\n");
 
+    yaml_token_t dummy_token;
+
+    printf("#[allow(non_camel_case_types)]\n");
+    printf("pub type yaml_token_data_t = [c_int; %lu];\n", sizeof(dummy_token.data) / sizeof(int));
+    printf("pub fn new_yaml_token_data_t() -> yaml_token_data_t {\n");
+    printf("    [0; %lu]\n", sizeof(dummy_token.data) / sizeof(int));
+    printf("}\n\n");
+
+    printf("#[allow(non_camel_case_types)]\n");
+    printf("#[repr(u%lu)]\n", ((size_t)(&dummy_token.data) - (size_t)(&dummy_token)) * 8);
+    printf("#[derive(Debug, PartialEq, Clone, Copy)]\n");
+    printf("pub enum yaml_token_type_t {\n");
+    printf("    /** An empty token. */\n");
+    printf("    YAML_NO_TOKEN = 0,\n\n");
+
+    printf("    /** A STREAM-START token. */\n");
+    printf("    YAML_STREAM_START_TOKEN,\n");
+    printf("    /** A STREAM-END token. */\n");
+    printf("    YAML_STREAM_END_TOKEN,\n\n");
+
+    printf("    /** A VERSION-DIRECTIVE token. */\n");
+    printf("    YAML_VERSION_DIRECTIVE_TOKEN,\n");
+    printf("    /** A TAG-DIRECTIVE token. */\n");
+    printf("    YAML_TAG_DIRECTIVE_TOKEN,\n");
+    printf("    /** A DOCUMENT-START token. */\n");
+    printf("    YAML_DOCUMENT_START_TOKEN,\n");
+    printf("    /** A DOCUMENT-END token. */\n");
+    printf("    YAML_DOCUMENT_END_TOKEN,\n\n");
+
+    printf("    /** A BLOCK-SEQUENCE-START token. */\n");
+    printf("    YAML_BLOCK_SEQUENCE_START_TOKEN,\n");
+    printf("    /** A BLOCK-MAPPING-START token. */\n");
+    printf("    YAML_BLOCK_MAPPING_START_TOKEN,\n");
+    printf("    /** A BLOCK-END token. */\n");
+    printf("    YAML_BLOCK_END_TOKEN,\n\n");
+
+    printf("    /** A FLOW-SEQUENCE-START token. */\n");
+    printf("    YAML_FLOW_SEQUENCE_START_TOKEN,\n");
+    printf("    /** A FLOW-SEQUENCE-END token. */\n");
+    printf("    YAML_FLOW_SEQUENCE_END_TOKEN,\n");
+    printf("    /** A FLOW-MAPPING-START token. */\n");
+    printf("    YAML_FLOW_MAPPING_START_TOKEN,\n");
+    printf("    /** A FLOW-MAPPING-END token. */\n");
+    printf("    YAML_FLOW_MAPPING_END_TOKEN,\n\n");
+
+    printf("    /** A BLOCK-ENTRY token. */\n");
+    printf("    YAML_BLOCK_ENTRY_TOKEN,\n");
+    printf("    /** A FLOW-ENTRY token. */\n");
+    printf("    YAML_FLOW_ENTRY_TOKEN,\n");
+    printf("    /** A KEY token. */\n");
+    printf("    YAML_KEY_TOKEN,\n");
+    printf("    /** A VALUE token. */\n");
+    printf("    YAML_VALUE_TOKEN,\n\n");
+
+    printf("    /** An ALIAS token. */\n");
+    printf("    YAML_ALIAS_TOKEN,\n");
+    printf("    /** An ANCHOR token. */\n");
+    printf("    YAML_ANCHOR_TOKEN,\n");
+    printf("    /** A TAG token. */\n");
+    printf("    YAML_TAG_TOKEN,\n");
+    printf("    /** A SCALAR token. */\n");
+    printf("    YAML_SCALAR_TOKEN\n");
+    printf("}\n\n");
+
     yaml_parser_t dummy_parser;
 
     printf("#[allow(non_camel_case_types)]\n");
@@ -83,6 +147,8 @@ int main()
     printf("#[cfg(test)]\n");
     printf("pub static YAML_EVENT_T_SIZE:usize = %lu;\n", sizeof(yaml_event_t));
     printf("#[cfg(test)]\n");
+    printf("pub static YAML_TOKEN_T_SIZE:usize = %lu;\n", sizeof(yaml_token_t));
+    printf("#[cfg(test)]\n");
     printf("pub static YAML_DOCUMENT_T_SIZE:usize = %lu;\n", sizeof(yaml_document_t));
     printf("#[cfg(test)]\n");
     printf("pub static YAML_NODE_T_SIZE:usize = %lu;\n", sizeof(yaml_node_t));
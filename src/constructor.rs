@@ -1,11 +1,21 @@
+use codecs;
 use document;
 use document::{YamlNode, YamlNodeData};
 use ffi::{YamlErrorType, YamlScalarStyle};
 use error::{YamlMark, YamlError, YamlErrorContext};
+use parser::YamlLimits;
 
 use std::f64;
-use std::char;
-use regex::Regex;
+use std::i64;
+use std::fmt;
+use std::mem;
+use std::hash::{Hash, Hasher};
+use std::num::IntErrorKind;
+use std::sync::Arc;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
+use regex::{Regex, Captures};
 
 pub trait YamlConstructor<T, E> {
     fn construct_scalar(&self, scalar: document::YamlScalarData) -> Result<T, E>;
@@ -21,30 +31,372 @@ pub trait YamlConstructor<T, E> {
     }
 }
 
-#[derive(PartialEq, Clone, Debug)]
+#[derive(PartialEq, Clone, Copy, Debug, Hash)]
+pub struct YamlTime {
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub nanosecond: u32,
+    // offset from UTC in minutes, None when the timestamp has no time zone
+    pub offset: Option<i32>
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, Hash)]
+pub struct YamlTimestamp {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    // None for date-only timestamps
+    pub time: Option<YamlTime>
+}
+
+impl fmt::Display for YamlTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day));
+
+        match self.time {
+            None => Ok(()),
+            Some(ref time) => {
+                try!(write!(f, "T{:02}:{:02}:{:02}", time.hour, time.minute, time.second));
+                if time.nanosecond > 0 {
+                    let fraction = format!("{:09}", time.nanosecond);
+                    try!(write!(f, ".{}", fraction.trim_end_matches('0')));
+                }
+                match time.offset {
+                    None => Ok(()),
+                    Some(0) => write!(f, "Z"),
+                    Some(offset) => {
+                        let sign = if offset < 0 { '-' } else { '+' };
+                        write!(f, "{}{:02}:{:02}", sign, offset.abs() / 60, offset.abs() % 60)
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub enum YamlStandardData {
-    YamlInteger(isize),
+    YamlInteger(i64),
+    // integers above i64::MAX which still fit in a u64
+    YamlUnsignedInteger(u64),
+    // integers too large for any of the fixed-width types, kept as written
+    YamlBigInteger(String),
     YamlFloat(f64),
     YamlString(String),
     YamlNull,
     YamlBool(bool),
+    YamlTimestamp(YamlTimestamp),
+    YamlBinary(Vec<u8>),
     YamlSequence(Vec<YamlStandardData>),
     YamlMapping(Vec<(YamlStandardData, YamlStandardData)>),
+    YamlSet(Vec<YamlStandardData>),
+    YamlOrderedMap(Vec<(YamlStandardData, YamlStandardData)>),
+    YamlPairs(Vec<(YamlStandardData, YamlStandardData)>),
+    // a node with an application-specific tag that has no registered handler
+    YamlTagged(String, Box<YamlStandardData>),
+    // a node referred to by aliases; every reference shares the same value.
+    // It compares equal to the value it holds.
+    YamlShared(Arc<YamlStandardData>),
+}
+
+impl YamlStandardData {
+    // the value itself, for shared values
+    pub fn resolved(&self) -> &YamlStandardData {
+        match *self {
+            YamlStandardData::YamlShared(ref data) => data.resolved(),
+            _ => self
+        }
+    }
+
+    fn into_unshared(self) -> YamlStandardData {
+        match self {
+            YamlStandardData::YamlShared(data) => data.resolved().clone(),
+            data => data
+        }
+    }
+}
+
+impl PartialEq for YamlStandardData {
+    fn eq(&self, other: &YamlStandardData) -> bool {
+        use self::YamlStandardData::*;
+
+        match (self.resolved(), other.resolved()) {
+            (&YamlInteger(a), &YamlInteger(b)) => a == b,
+            (&YamlUnsignedInteger(a), &YamlUnsignedInteger(b)) => a == b,
+            (&YamlBigInteger(ref a), &YamlBigInteger(ref b)) => a == b,
+            (&YamlFloat(a), &YamlFloat(b)) => a == b,
+            (&YamlString(ref a), &YamlString(ref b)) => a == b,
+            (&YamlNull, &YamlNull) => true,
+            (&YamlBool(a), &YamlBool(b)) => a == b,
+            (&YamlTimestamp(ref a), &YamlTimestamp(ref b)) => a == b,
+            (&YamlBinary(ref a), &YamlBinary(ref b)) => a == b,
+            (&YamlSequence(ref a), &YamlSequence(ref b)) => a == b,
+            (&YamlMapping(ref a), &YamlMapping(ref b)) => a == b,
+            (&YamlSet(ref a), &YamlSet(ref b)) => a == b,
+            (&YamlOrderedMap(ref a), &YamlOrderedMap(ref b)) => a == b,
+            (&YamlPairs(ref a), &YamlPairs(ref b)) => a == b,
+            (&YamlTagged(ref tag_a, ref a), &YamlTagged(ref tag_b, ref b)) => tag_a == tag_b && a == b,
+            _ => false
+        }
+    }
+}
+
+// Handlers receive the constructor itself, so that they can construct child
+// nodes with the same settings
+pub type YamlTagHandler = Arc<Fn(&YamlStandardConstructor, YamlNode) -> Result<YamlStandardData, YamlError> + Send + Sync>;
+
+// The schema decides how untagged plain scalars are resolved. Explicit tags are
+// honoured under every schema.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum YamlSchema {
+    // every plain scalar is a string
+    YamlFailsafeSchema,
+    YamlJsonSchema,
+    YamlCoreSchema,
+    // YAML 1.1 types, including yes/no/on/off booleans, sexagesimal numbers
+    // and timestamps
+    Yaml11Schema,
+    // the resolution YamlStandardConstructor::new() has always used: YAML 1.1
+    // integers, yes/no booleans and timestamps, without on/off or sexagesimal
+    // numbers
+    YamlDefaultSchema,
+}
+
+fn limit_error(message: &str, mark: &YamlMark) -> YamlError {
+    let mut err = standard_error(message.to_string(), mark);
+    err.limit_exceeded = true;
+    err
+}
+
+fn exceeds(limit: Option<usize>, value: usize) -> bool {
+    limit.map_or(false, |max| value > max)
+}
+
+// Hashes a value consistently with its PartialEq
+fn hash_data<H: Hasher>(data: &YamlStandardData, state: &mut H) {
+    let data = data.resolved();
+    mem::discriminant(data).hash(state);
+    match *data {
+        YamlStandardData::YamlInteger(i) => i.hash(state),
+        YamlStandardData::YamlUnsignedInteger(u) => u.hash(state),
+        YamlStandardData::YamlBigInteger(ref s) | YamlStandardData::YamlString(ref s) => s.hash(state),
+        // 0.0 and -0.0 are equal, and NaN is equal to nothing
+        YamlStandardData::YamlFloat(f) => (if f == 0.0 { 0 } else { f.to_bits() }).hash(state),
+        YamlStandardData::YamlBool(b) => b.hash(state),
+        YamlStandardData::YamlTimestamp(ref t) => t.hash(state),
+        YamlStandardData::YamlBinary(ref bytes) => bytes.hash(state),
+        YamlStandardData::YamlSequence(ref items) | YamlStandardData::YamlSet(ref items) =>
+            for item in items.iter() {
+                hash_data(item, state);
+            },
+        YamlStandardData::YamlMapping(ref pairs) | YamlStandardData::YamlOrderedMap(ref pairs) |
+                YamlStandardData::YamlPairs(ref pairs) =>
+            for &(ref key, ref value) in pairs.iter() {
+                hash_data(key, state);
+                hash_data(value, state);
+            },
+        YamlStandardData::YamlTagged(ref tag, ref data) => {
+            tag.hash(state);
+            hash_data(data, state);
+        },
+        YamlStandardData::YamlNull | YamlStandardData::YamlShared(_) => ()
+    }
+}
+
+fn key_hash(key: &YamlStandardData) -> u64 {
+    let mut state = DefaultHasher::new();
+    hash_data(key, &mut state);
+    state.finish()
+}
+
+// Finds the earlier keys of a collection which are equal to a key, without
+// comparing it with every one of them. YamlStandardData can't implement Eq
+// because of NaN, so keys with the same hash are compared by the caller.
+struct YamlKeyIndex {
+    buckets: HashMap<u64, Vec<usize>>
+}
+
+impl YamlKeyIndex {
+    fn new() -> YamlKeyIndex {
+        YamlKeyIndex {
+            buckets: HashMap::new()
+        }
+    }
+
+    fn find<F: Fn(usize) -> bool>(&self, hash: u64, is_equal: F) -> Option<usize> {
+        self.buckets.get(&hash).and_then(|positions| positions.iter().cloned().find(|&i| is_equal(i)))
+    }
+
+    fn insert(&mut self, hash: u64, position: usize) {
+        self.buckets.entry(hash).or_insert_with(Vec::new).push(position);
+    }
+}
+
+// Values which can stand for every reference to an aliased node without
+// copying it
+trait YamlShare: Clone {
+    fn share(self) -> Self;
+}
+
+impl YamlShare for YamlStandardData {
+    fn share(self) -> YamlStandardData {
+        match self {
+            YamlStandardData::YamlShared(data) => YamlStandardData::YamlShared(data),
+            data => YamlStandardData::YamlShared(Arc::new(data))
+        }
+    }
+}
+
+// Keeps track of the nodes under construction, so that recursive aliases
+// are reported instead of overflowing the stack, and remembers the shared
+// values of aliased nodes along with their node counts, so that each of them
+// is constructed only once and never copied. The nodes of an aliased value
+// still count against max_nodes for every reference to it.
+#[derive(Clone)]
+struct YamlAliasTracker<T> {
+    in_progress: HashSet<usize>,
+    cache: HashMap<usize, (T, usize)>,
+    nodes: usize,
+    dereferences: usize
+}
+
+impl<T: YamlShare> YamlAliasTracker<T> {
+    fn new() -> YamlAliasTracker<T> {
+        YamlAliasTracker {
+            in_progress: HashSet::new(),
+            cache: HashMap::new(),
+            nodes: 0,
+            dereferences: 0
+        }
+    }
+
+    fn reset(&mut self) {
+        self.cache.clear();
+        self.nodes = 0;
+        self.dereferences = 0;
+    }
+}
+
+fn construct_tracked<T, F>(tracker: &RefCell<YamlAliasTracker<T>>, limits: &YamlLimits, node: YamlNode, f: F) -> Result<T, YamlError>
+    where T: YamlShare, F: FnOnce(YamlNode) -> Result<T, YamlError>
+{
+    let id = node.node_id();
+    let aliased = node.is_aliased();
+    let nodes_before;
+    {
+        let mut tracker = tracker.borrow_mut();
+        nodes_before = tracker.nodes;
+        match enter_node(&mut tracker, limits, &node, id, aliased) {
+            Ok(Some(value)) => return Ok(value),
+            Ok(None) => (),
+            Err(e) => {
+                if tracker.in_progress.is_empty() {
+                    tracker.reset();
+                }
+                return Err(e);
+            }
+        }
+        tracker.in_progress.insert(id);
+    }
+
+    let res = if aliased { f(node).map(|value| value.share()) } else { f(node) };
+
+    let mut tracker = tracker.borrow_mut();
+    tracker.in_progress.remove(&id);
+    if tracker.in_progress.is_empty() {
+        // the outermost node is done, and ids are only unique within a document
+        tracker.reset();
+    } else if aliased {
+        if let Ok(ref value) = res {
+            let size = tracker.nodes - nodes_before;
+            tracker.cache.insert(id, (value.clone(), size));
+        }
+    }
+    res
+}
+
+// Returns the cached value when the node has already been constructed
+fn enter_node<T: YamlShare>(tracker: &mut YamlAliasTracker<T>, limits: &YamlLimits, node: &YamlNode,
+        id: usize, aliased: bool) -> Result<Option<T>, YamlError> {
+    let mark = node_start_mark(node);
+    if tracker.in_progress.contains(&id) {
+        return Err(standard_error("found a recursive alias".to_string(), &mark));
+    }
+
+    if aliased {
+        if let Some(&(ref value, size)) = tracker.cache.get(&id) {
+            tracker.dereferences += 1;
+            tracker.nodes += size;
+            if exceeds(limits.max_alias_dereferences, tracker.dereferences) {
+                return Err(limit_error("exceeded the maximum number of alias dereferences", &mark));
+            }
+            if exceeds(limits.max_nodes, tracker.nodes) {
+                return Err(limit_error("exceeded the maximum number of nodes", &mark));
+            }
+            return Ok(Some(value.clone()));
+        }
+    }
+
+    tracker.nodes += 1;
+    if exceeds(limits.max_nodes, tracker.nodes) {
+        return Err(limit_error("exceeded the maximum number of nodes", &mark));
+    }
+    let is_collection = match *node {
+        YamlNode::YamlScalarNode(_) => false,
+        _ => true
+    };
+    if is_collection && exceeds(limits.max_depth, tracker.in_progress.len() + 1) {
+        return Err(limit_error("exceeded the maximum nesting depth", &mark));
+    }
+
+    Ok(None)
+}
+
+pub struct YamlConstructorOptions {
+    pub schema: YamlSchema,
+    pub merge_keys: bool,
+    pub unique_keys: bool,
+    pub limits: YamlLimits
+}
+
+impl Default for YamlConstructorOptions {
+    fn default() -> YamlConstructorOptions {
+        YamlConstructorOptions {
+            schema: YamlSchema::YamlDefaultSchema,
+            merge_keys: true,
+            unique_keys: false,
+            limits: Default::default()
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct YamlStandardConstructor {
-    dec_int_pat:Regex,
-    oct_int_pat:Regex,
-    hex_int_pat:Regex,
-    bin_int_pat:Regex,
-    flt_pat:Regex,
-    pos_inf_pat:Regex,
-    neg_inf_pat:Regex,
-    nan_pat:Regex,
-    null_pat:Regex,
-    true_pat:Regex,
-    false_pat:Regex
+    schema:YamlSchema,
+    dec_int_pat:Option<Regex>,
+    oct_int_pat:Option<Regex>,
+    hex_int_pat:Option<Regex>,
+    bin_int_pat:Option<Regex>,
+    sexagesimal_int_pat:Option<Regex>,
+    flt_pat:Option<Regex>,
+    sexagesimal_flt_pat:Option<Regex>,
+    pos_inf_pat:Option<Regex>,
+    neg_inf_pat:Option<Regex>,
+    nan_pat:Option<Regex>,
+    null_pat:Option<Regex>,
+    true_pat:Option<Regex>,
+    false_pat:Option<Regex>,
+    date_pat:Regex,
+    timestamp_pat:Regex,
+    // failsafe has no scalar types of its own, so explicitly tagged scalars
+    // are resolved with the core schema instead
+    tagged_resolver:Option<Box<YamlStandardConstructor>>,
+    tag_handlers:HashMap<String, YamlTagHandler>,
+    merge_keys:bool,
+    unique_keys:bool,
+    limits:YamlLimits,
+    aliases:RefCell<YamlAliasTracker<YamlStandardData>>
 }
 
 fn standard_error(message: String, mark: &YamlMark) -> YamlError {
@@ -59,269 +411,1341 @@ fn standard_error(message: String, mark: &YamlMark) -> YamlError {
         kind: YamlErrorType::YAML_PARSER_ERROR,
         problem: Some(message),
         io_error: None,
-        context: Some(context)
+        context: Some(context),
+        limit_exceeded: false
     }
 }
 
-fn take(iter: &mut Iterator<Item=char>, n: usize) -> String
-{
-    let mut s = String::new();
-    for _ in 0..n {
-        match iter.next() {
-            Some(c) => s.push(c),
-            None => break
-        }
+const STR_TAG: &'static str = "tag:yaml.org,2002:str";
+const INT_TAG: &'static str = "tag:yaml.org,2002:int";
+const FLOAT_TAG: &'static str = "tag:yaml.org,2002:float";
+const BOOL_TAG: &'static str = "tag:yaml.org,2002:bool";
+const NULL_TAG: &'static str = "tag:yaml.org,2002:null";
+const SEQ_TAG: &'static str = "tag:yaml.org,2002:seq";
+const MAP_TAG: &'static str = "tag:yaml.org,2002:map";
+const TIMESTAMP_TAG: &'static str = "tag:yaml.org,2002:timestamp";
+const MERGE_TAG: &'static str = "tag:yaml.org,2002:merge";
+pub const BINARY_TAG: &'static str = "tag:yaml.org,2002:binary";
+pub const SET_TAG: &'static str = "tag:yaml.org,2002:set";
+pub const OMAP_TAG: &'static str = "tag:yaml.org,2002:omap";
+pub const PAIRS_TAG: &'static str = "tag:yaml.org,2002:pairs";
+
+fn is_core_tag(tag: &str) -> bool {
+    [STR_TAG, INT_TAG, FLOAT_TAG, BOOL_TAG, NULL_TAG, SEQ_TAG, MAP_TAG, TIMESTAMP_TAG, BINARY_TAG,
+        SET_TAG, OMAP_TAG, PAIRS_TAG].contains(&tag)
+}
+
+fn tag_mismatch_error(tag: &str, mark: &YamlMark) -> YamlError {
+    standard_error(format!("value does not match its tag {}", tag), mark)
+}
+
+fn duplicate_key_error(first: &YamlMark, second: &YamlMark) -> YamlError {
+    let context = YamlErrorContext {
+        byte_offset: second.index,
+        problem_mark: *second,
+        context: Some("found duplicate key; first occurrence".to_string()),
+        context_mark: *first,
+    };
+
+    YamlError {
+        kind: YamlErrorType::YAML_PARSER_ERROR,
+        problem: Some("second occurrence".to_string()),
+        io_error: None,
+        context: Some(context),
+        limit_exceeded: false
+    }
+}
+
+fn node_start_mark(node: &YamlNode) -> YamlMark {
+    match *node {
+        YamlNode::YamlScalarNode(ref scalar) => scalar.start_mark(),
+        YamlNode::YamlSequenceNode(ref sequence) => sequence.start_mark(),
+        YamlNode::YamlMappingNode(ref mapping) => mapping.start_mark()
     }
-    return s;
 }
 
 impl YamlStandardConstructor {
     pub fn new() -> YamlStandardConstructor {
-        YamlStandardConstructor {
-            dec_int_pat: Regex::new(r"^[-+]?(0|[1-9][0-9_]*)$").unwrap(),
-            oct_int_pat: Regex::new(r"^([-+]?)0o?([0-7_]+)$").unwrap(),
-            hex_int_pat: Regex::new(r"^([-+]?)0x([0-9a-fA-F_]+)$").unwrap(),
-            bin_int_pat: Regex::new(r"^([-+]?)0b([0-1_]+)$").unwrap(),
-            flt_pat: Regex::new(r"^([-+]?)(\.[0-9]+|[0-9]+(\.[0-9]*)?([eE][-+]?[0-9]+)?)$").unwrap(),
-            pos_inf_pat: Regex::new(r"^[+]?(\.inf|\.Inf|\.INF)$").unwrap(),
-            neg_inf_pat: Regex::new(r"^-(\.inf|\.Inf|\.INF)$").unwrap(),
-            nan_pat: Regex::new(r"^(\.nan|\.NaN|\.NAN)$").unwrap(),
-            null_pat: Regex::new(r"^(null|Null|NULL|~)$").unwrap(),
-            true_pat: Regex::new(r"^(true|True|TRUE|yes|Yes|YES)$").unwrap(),
-            false_pat: Regex::new(r"^(false|False|FALSE|no|No|NO)$").unwrap()
-        }
-    }
-
-    fn parse_double_quoted(value: &str, mark: &YamlMark) -> Result<String, YamlError> {
-        let mut buf = String::new();
-        let mut it = value.chars();
-
-        loop {
-            match it.next() {
-                None => return Ok(buf),
-                Some('\\') => {
-                    // escape sequences
-                    match it.next() {
-                        None => return Err(standard_error(
-                                    "unexpected end of string after escape".to_string(),
-                                    mark
-                                )),
-                        Some('0') => buf.push('\x00'),              // null
-                        Some('a') => buf.push('\x07'),              // ASCII bell
-                        Some('b') => buf.push('\x08'),              // backspace
-                        Some('t') | Some('\t') => buf.push('\t'),   // horizontal tab
-                        Some('n') => buf.push('\n'),                // linefeed
-                        Some('v') => buf.push('\x0b'),              // vertical tab
-                        Some('f') => buf.push('\x0c'),              // form feed
-                        Some('r') => buf.push('\x0d'),              // carriage return
-                        Some('e') => buf.push('\x1b'),              // ASCII escape
-                        Some('N') => buf.push('\u{85}'),            // unicode next line
-                        Some('_') => buf.push('\u{a0}'),            // unicode non-breaking space
-                        Some('L') => buf.push('\u{2028}'),          // unicode line separator
-                        Some('P') => buf.push('\u{2029}'),          // unicode paragraph separator
-                        Some('x') => {
-                            let code:String = take(&mut it, 2);
-                            match parse_escape_sequence(&code[..], 2) {
-                                Some(c) => buf.push(c),
-                                None => return Err(standard_error(
-                                            format!("invalid escape sequence {}", code),
-                                            mark
-                                        ))
-                            }
-                        },
-                        Some('u') => {
-                            let code:String = take(&mut it, 4);
-                            match parse_escape_sequence(&code[..], 4) {
-                                Some(c) => buf.push(c),
-                                None => return Err(standard_error(
-                                            format!("invalid escape sequence {}", code),
-                                            mark
-                                        ))
-                            }
-                        },
-                        Some('U') => {
-                            let code:String = take(&mut it, 8);
-                            match parse_escape_sequence(&code[..], 8) {
-                                Some(c) => buf.push(c),
-                                None => return Err(standard_error(
-                                            format!("invalid escape sequence {}", code),
-                                            mark
-                                        ))
-                            }
-                        },
-                        Some(c) => buf.push(c)
+        YamlStandardConstructor::with_schema(YamlSchema::YamlDefaultSchema)
+    }
+
+    pub fn with_schema(schema: YamlSchema) -> YamlStandardConstructor {
+        let pat = |re: &str| Some(Regex::new(re).unwrap());
+
+        let mut ctor = YamlStandardConstructor {
+            schema: schema,
+            dec_int_pat: None,
+            oct_int_pat: None,
+            hex_int_pat: None,
+            bin_int_pat: None,
+            sexagesimal_int_pat: None,
+            flt_pat: None,
+            sexagesimal_flt_pat: None,
+            pos_inf_pat: None,
+            neg_inf_pat: None,
+            nan_pat: None,
+            null_pat: None,
+            true_pat: None,
+            false_pat: None,
+            date_pat: Regex::new(r"^([0-9]{4})-([0-9]{2})-([0-9]{2})$").unwrap(),
+            timestamp_pat: Regex::new(concat!(
+                r"^([0-9]{4})-([0-9]{1,2})-([0-9]{1,2})",
+                r"(?:[Tt]|[ \t]+)([0-9]{1,2}):([0-9]{2}):([0-9]{2})(?:\.([0-9]*))?",
+                r"(?:[ \t]*(Z|([-+])([0-9]{1,2})(?::([0-9]{2}))?))?$")).unwrap(),
+            tagged_resolver: None,
+            tag_handlers: HashMap::new(),
+            merge_keys: true,
+            unique_keys: false,
+            limits: Default::default(),
+            aliases: RefCell::new(YamlAliasTracker::new())
+        };
+
+        match schema {
+            YamlSchema::YamlFailsafeSchema => {
+                ctor.tagged_resolver = Some(Box::new(YamlStandardConstructor::with_schema(YamlSchema::YamlCoreSchema)));
+            },
+            YamlSchema::YamlJsonSchema => {
+                ctor.dec_int_pat = pat(r"^(-?)(0|[1-9][0-9]*)$");
+                ctor.flt_pat = pat(r"^(-?)((?:0|[1-9][0-9]*)(?:\.[0-9]*)?(?:[eE][-+]?[0-9]+)?)$");
+                ctor.null_pat = pat(r"^null$");
+                ctor.true_pat = pat(r"^true$");
+                ctor.false_pat = pat(r"^false$");
+            },
+            YamlSchema::YamlCoreSchema => {
+                ctor.dec_int_pat = pat(r"^([-+]?)([0-9]+)$");
+                ctor.oct_int_pat = pat(r"^()0o([0-7]+)$");
+                ctor.hex_int_pat = pat(r"^()0x([0-9a-fA-F]+)$");
+                ctor.flt_pat = pat(r"^([-+]?)((?:\.[0-9]+|[0-9]+(?:\.[0-9]*)?)(?:[eE][-+]?[0-9]+)?)$");
+                ctor.pos_inf_pat = pat(r"^[+]?(\.inf|\.Inf|\.INF)$");
+                ctor.neg_inf_pat = pat(r"^-(\.inf|\.Inf|\.INF)$");
+                ctor.nan_pat = pat(r"^(\.nan|\.NaN|\.NAN)$");
+                // an empty plain scalar, such as the value in "a:", is null
+                ctor.null_pat = pat(r"^(null|Null|NULL|~|)$");
+                ctor.true_pat = pat(r"^(true|True|TRUE)$");
+                ctor.false_pat = pat(r"^(false|False|FALSE)$");
+            },
+            YamlSchema::Yaml11Schema => {
+                ctor.dec_int_pat = pat(r"^([-+]?)(0|[1-9][0-9_]*)$");
+                ctor.oct_int_pat = pat(r"^([-+]?)0o?([0-7_]+)$");
+                ctor.hex_int_pat = pat(r"^([-+]?)0x([0-9a-fA-F_]+)$");
+                ctor.bin_int_pat = pat(r"^([-+]?)0b([0-1_]+)$");
+                ctor.sexagesimal_int_pat = pat(r"^([-+]?)([1-9][0-9_]*(?::[0-5]?[0-9])+)$");
+                ctor.flt_pat = pat(r"^([-+]?)(\.[0-9_]+|[0-9][0-9_]*(?:\.[0-9_]*(?:[eE][-+]?[0-9]+)?|[eE][-+]?[0-9]+))$");
+                ctor.sexagesimal_flt_pat = pat(r"^([-+]?)([0-9][0-9_]*(?::[0-5]?[0-9])+\.[0-9_]*)$");
+                ctor.pos_inf_pat = pat(r"^[+]?(\.inf|\.Inf|\.INF)$");
+                ctor.neg_inf_pat = pat(r"^-(\.inf|\.Inf|\.INF)$");
+                ctor.nan_pat = pat(r"^(\.nan|\.NaN|\.NAN)$");
+                ctor.null_pat = pat(r"^(null|Null|NULL|~|)$");
+                ctor.true_pat = pat(r"^(true|True|TRUE|yes|Yes|YES|on|On|ON)$");
+                ctor.false_pat = pat(r"^(false|False|FALSE|no|No|NO|off|Off|OFF)$");
+            },
+            YamlSchema::YamlDefaultSchema => {
+                ctor.dec_int_pat = pat(r"^([-+]?)(0|[1-9][0-9_]*)$");
+                ctor.oct_int_pat = pat(r"^([-+]?)0o?([0-7_]+)$");
+                ctor.hex_int_pat = pat(r"^([-+]?)0x([0-9a-fA-F_]+)$");
+                ctor.bin_int_pat = pat(r"^([-+]?)0b([0-1_]+)$");
+                ctor.flt_pat = pat(r"^([-+]?)(\.[0-9]+|[0-9]+(?:\.[0-9]*)?(?:[eE][-+]?[0-9]+)?)$");
+                ctor.pos_inf_pat = pat(r"^[+]?(\.inf|\.Inf|\.INF)$");
+                ctor.neg_inf_pat = pat(r"^-(\.inf|\.Inf|\.INF)$");
+                ctor.nan_pat = pat(r"^(\.nan|\.NaN|\.NAN)$");
+                ctor.null_pat = pat(r"^(null|Null|NULL|~|)$");
+                ctor.true_pat = pat(r"^(true|True|TRUE|yes|Yes|YES)$");
+                ctor.false_pat = pat(r"^(false|False|FALSE|no|No|NO)$");
+            }
+        }
+
+        ctor
+    }
+
+    pub fn schema(&self) -> YamlSchema {
+        self.schema
+    }
+
+    fn resolve_tagged_scalar(&self, value: String) -> YamlStandardData {
+        match self.tagged_resolver {
+            Some(ref resolver) => resolver.resolve_plain_scalar(value),
+            None => self.resolve_plain_scalar(value)
+        }
+    }
+
+    pub fn with_options(options: &YamlConstructorOptions) -> YamlStandardConstructor {
+        let mut ctor = YamlStandardConstructor::with_schema(options.schema);
+        ctor.set_merge_keys(options.merge_keys);
+        ctor.set_unique_keys(options.unique_keys);
+        ctor.set_limits(options.limits);
+        ctor
+    }
+
+    // merge keys (<<) are expanded by default
+    pub fn set_merge_keys(&mut self, enabled: bool) {
+        self.merge_keys = enabled;
+    }
+
+    // When enabled, a mapping with two keys of equal value is an error. Keys
+    // are compared after construction, so 1 and 0x1 are the same key. Keys
+    // brought in by merge keys may still be overridden.
+    pub fn set_unique_keys(&mut self, enabled: bool) {
+        self.unique_keys = enabled;
+    }
+
+    // Only the depth, node count and alias dereference limits apply to
+    // construction. The others are enforced when the document is loaded.
+    //
+    // Aliased values are shared rather than copied, but max_nodes still
+    // counts their nodes once for every reference, so that code walking the
+    // result doesn't have to expect an exponentially large value.
+    pub fn set_limits(&mut self, limits: YamlLimits) {
+        self.limits = limits;
+    }
+
+    // The tag is matched against the fully resolved tag of the node, so local
+    // tags are registered as written ("!Ref") and tags using a %TAG handle by
+    // their global URI. Handlers take priority over the built-in types.
+    pub fn register_tag<F>(&mut self, tag: &str, handler: F)
+        where F: Fn(&YamlStandardConstructor, YamlNode) -> Result<YamlStandardData, YamlError> + Send + Sync + 'static
+    {
+        self.tag_handlers.insert(tag.to_string(), Arc::new(handler));
+    }
+
+    fn tag_handler<N: YamlNodeData>(&self, node: &N) -> Option<YamlTagHandler> {
+        if self.tag_handlers.is_empty() || !node.is_tag_explicit() {
+            return None;
+        }
+
+        node.tag().and_then(|tag| self.tag_handlers.get(&tag).cloned())
+    }
+
+    fn construct_untagged_sequence(&self, sequence: document::YamlSequenceData) -> Result<YamlStandardData, YamlError> {
+        let res:Result<Vec<YamlStandardData>, YamlError> = sequence.values().map(|node| { self.construct(node) }).collect();
+        res.map(|list| YamlStandardData::YamlSequence(list))
+    }
+
+    fn construct_untagged_mapping(&self, mapping: document::YamlMappingData) -> Result<YamlStandardData, YamlError> {
+        let mut pairs: Vec<(YamlStandardData, YamlStandardData)> = Vec::new();
+        let mut key_marks = Vec::new();
+        let mut keys = YamlKeyIndex::new();
+        let mut merged = Vec::new();
+        for (key_node, value_node) in mapping.pairs() {
+            if self.is_merge_key(&key_node) {
+                try!(self.construct_merge(value_node, &mut merged));
+            } else {
+                let key_mark = node_start_mark(&key_node);
+                let key = try!(self.construct(key_node));
+                if self.unique_keys {
+                    let hash = key_hash(&key);
+                    if let Some(i) = keys.find(hash, |i| pairs[i].0 == key) {
+                        return Err(duplicate_key_error(&key_marks[i], &key_mark));
                     }
+                    keys.insert(hash, pairs.len());
+                }
+                let value = try!(self.construct(value_node));
+                pairs.push((key, value));
+                key_marks.push(key_mark);
+            }
+        }
+
+        if merged.is_empty() {
+            return Ok(YamlStandardData::YamlMapping(pairs));
+        }
+
+        // keys of the mapping itself override merged keys, and earlier merges
+        // take priority over later ones. Positions past the end of pairs
+        // refer to res.
+        let mut keys = YamlKeyIndex::new();
+        for (i, &(ref key, _)) in pairs.iter().enumerate() {
+            keys.insert(key_hash(key), i);
+        }
+        let mut res: Vec<(YamlStandardData, YamlStandardData)> = Vec::new();
+        for (key, value) in merged.into_iter().flat_map(|p| p.into_iter()) {
+            let hash = key_hash(&key);
+            let found = keys.find(hash, |i| if i < pairs.len() { pairs[i].0 == key } else { res[i - pairs.len()].0 == key });
+            if found.is_none() {
+                keys.insert(hash, pairs.len() + res.len());
+                res.push((key, value));
+            }
+        }
+        res.extend(pairs.into_iter());
+        Ok(YamlStandardData::YamlMapping(res))
+    }
+
+    // !!omap and !!pairs are sequences of single-pair mappings
+    fn construct_pairs(&self, sequence: document::YamlSequenceData, unique: bool) -> Result<YamlStandardData, YamlError> {
+        let mut pairs: Vec<(YamlStandardData, YamlStandardData)> = Vec::new();
+        let mut keys = YamlKeyIndex::new();
+
+        for node in sequence.values() {
+            let (mark, mut entries) = match node {
+                YamlNode::YamlMappingNode(ref mapping) => (mapping.start_mark(), mapping.pairs()),
+                YamlNode::YamlScalarNode(ref scalar) =>
+                    return Err(standard_error("expected a single-pair mapping".to_string(), &scalar.start_mark())),
+                YamlNode::YamlSequenceNode(ref seq) =>
+                    return Err(standard_error("expected a single-pair mapping".to_string(), &seq.start_mark())),
+            };
+
+            let (key_node, value_node) = match (entries.next(), entries.next()) {
+                (Some(pair), None) => pair,
+                _ => return Err(standard_error("expected a single-pair mapping".to_string(), &mark))
+            };
+            let key = try!(self.construct(key_node));
+            if unique {
+                let hash = key_hash(&key);
+                if keys.find(hash, |i| pairs[i].0 == key).is_some() {
+                    return Err(standard_error("found duplicate key in ordered mapping".to_string(), &mark));
+                }
+                keys.insert(hash, pairs.len());
+            }
+            let value = try!(self.construct(value_node));
+            pairs.push((key, value));
+        }
+
+        if unique {
+            Ok(YamlStandardData::YamlOrderedMap(pairs))
+        } else {
+            Ok(YamlStandardData::YamlPairs(pairs))
+        }
+    }
+
+    fn construct_set(&self, mapping: document::YamlMappingData) -> Result<YamlStandardData, YamlError> {
+        let mut keys = Vec::new();
+        let mut index = YamlKeyIndex::new();
+
+        for (key_node, value_node) in mapping.pairs() {
+            let mark = node_start_mark(&value_node);
+            let empty = match value_node {
+                YamlNode::YamlScalarNode(ref scalar) => scalar.style() == YamlScalarStyle::YamlPlainScalarStyle
+                    && !scalar.is_tag_explicit() && scalar.get_value().is_empty(),
+                _ => false
+            };
+            if !empty && try!(self.construct(value_node)) != YamlStandardData::YamlNull {
+                return Err(standard_error("expected null values in set".to_string(), &mark));
+            }
+
+            let key_mark = node_start_mark(&key_node);
+            let key = try!(self.construct(key_node));
+            let hash = key_hash(&key);
+            if index.find(hash, |i| keys[i] == key).is_some() {
+                return Err(standard_error("found duplicate key in set".to_string(), &key_mark));
+            }
+            index.insert(hash, keys.len());
+            keys.push(key);
+        }
+
+        Ok(YamlStandardData::YamlSet(keys))
+    }
+
+    fn is_merge_key(&self, node: &YamlNode) -> bool {
+        match *node {
+            YamlNode::YamlScalarNode(ref scalar) if self.merge_keys => {
+                if scalar.is_tag_explicit() {
+                    scalar.tag().map_or(false, |tag| &tag[..] == MERGE_TAG)
+                } else {
+                    scalar.style() == YamlScalarStyle::YamlPlainScalarStyle && scalar.get_value() == "<<"
+                }
+            },
+            _ => false
+        }
+    }
+
+    fn construct_merge(&self, node: YamlNode, merged: &mut Vec<Vec<(YamlStandardData, YamlStandardData)>>) -> Result<(), YamlError> {
+        let mark = node_start_mark(&node);
+        let error = || standard_error("expected a mapping or list of mappings for merging".to_string(), &mark);
+
+        match try!(self.construct(node)).into_unshared() {
+            YamlStandardData::YamlMapping(pairs) => merged.push(pairs),
+            YamlStandardData::YamlSequence(items) => for item in items.into_iter() {
+                match item.into_unshared() {
+                    YamlStandardData::YamlMapping(pairs) => merged.push(pairs),
+                    _ => return Err(error())
+                }
+            },
+            _ => return Err(error())
+        }
+
+        Ok(())
+    }
+
+    // resolves a scalar with an explicit tag, the way construct_scalar does
+    // when no tag handler is registered for it
+    pub fn resolve_explicit_scalar(&self, tag: String, string: String, plain: bool, mark: &YamlMark) -> Result<YamlStandardData, YamlError> {
+        match &tag[..] {
+            STR_TAG => Ok(YamlStandardData::YamlString(string)),
+            INT_TAG => match self.resolve_tagged_scalar(string) {
+                YamlStandardData::YamlInteger(i) => Ok(YamlStandardData::YamlInteger(i)),
+                YamlStandardData::YamlUnsignedInteger(u) => Ok(YamlStandardData::YamlUnsignedInteger(u)),
+                YamlStandardData::YamlBigInteger(s) =>
+                    Err(standard_error(format!("integer {} is out of range", s), mark)),
+                _ => Err(tag_mismatch_error(&tag, mark))
+            },
+            FLOAT_TAG => match self.resolve_tagged_scalar(string) {
+                YamlStandardData::YamlFloat(f) => Ok(YamlStandardData::YamlFloat(f)),
+                YamlStandardData::YamlInteger(i) => Ok(YamlStandardData::YamlFloat(i as f64)),
+                YamlStandardData::YamlUnsignedInteger(u) => Ok(YamlStandardData::YamlFloat(u as f64)),
+                YamlStandardData::YamlBigInteger(ref lexical) => match self.big_integer_to_float(&lexical[..]) {
+                    Some(f) => Ok(YamlStandardData::YamlFloat(f)),
+                    None => Err(tag_mismatch_error(&tag, mark))
                 },
-                Some(c) => buf.push(c)
+                _ => Err(tag_mismatch_error(&tag, mark))
+            },
+            BOOL_TAG => match self.resolve_tagged_scalar(string) {
+                YamlStandardData::YamlBool(b) => Ok(YamlStandardData::YamlBool(b)),
+                _ => Err(tag_mismatch_error(&tag, mark))
+            },
+            NULL_TAG => match self.resolve_tagged_scalar(string) {
+                YamlStandardData::YamlNull => Ok(YamlStandardData::YamlNull),
+                YamlStandardData::YamlString(ref s) if s.is_empty() => Ok(YamlStandardData::YamlNull),
+                _ => Err(tag_mismatch_error(&tag, mark))
+            },
+            TIMESTAMP_TAG => match self.resolve_timestamp(&string[..]) {
+                Some(timestamp) => Ok(YamlStandardData::YamlTimestamp(timestamp)),
+                None => Err(tag_mismatch_error(&tag, mark))
+            },
+            BINARY_TAG => match codecs::decode_base64(&string[..]) {
+                Some(bytes) => Ok(YamlStandardData::YamlBinary(bytes)),
+                None => Err(tag_mismatch_error(&tag, mark))
+            },
+            SEQ_TAG | MAP_TAG | SET_TAG | OMAP_TAG | PAIRS_TAG => Err(tag_mismatch_error(&tag, mark)),
+            MERGE_TAG => Ok(self.resolve_tagged_scalar(string)),
+            _ => {
+                let data = if plain {
+                    self.resolve_plain_scalar(string)
+                } else {
+                    YamlStandardData::YamlString(string)
+                };
+                Ok(YamlStandardData::YamlTagged(tag, Box::new(data)))
             }
         }
     }
-}
 
-fn parse_escape_sequence(rep: &str, expected_len: usize) -> Option<char> {
-    match u32::from_str_radix(rep, 16) {
-        Ok(code) if rep.len() == expected_len => char::from_u32(code),
-        _ => None
+    // an integer too large for u64 can still be represented approximately as a float
+    fn big_integer_to_float(&self, value: &str) -> Option<f64> {
+        let radix_pats = [(&self.bin_int_pat, 2), (&self.oct_int_pat, 8), (&self.hex_int_pat, 16), (&self.dec_int_pat, 10)];
+        for &(pat, radix) in radix_pats.iter() {
+            if let Some(caps) = captures(pat, value) {
+                return parse_float_radix(&caps[1], &caps[2], radix);
+            }
+        }
+
+        captures(&self.sexagesimal_int_pat, value).and_then(|caps| parse_sexagesimal_float(&caps[1], &caps[2]))
     }
-}
 
-fn parse_int(sign: &str, data: &str, radix: u32) -> isize {
-    let sign_flag = if sign == "-" {
-            -1
+    pub fn resolve_plain_scalar(&self, value: String) -> YamlStandardData {
+        match captures(&self.bin_int_pat, &value[..]).and_then(|caps| parse_int(&caps[1], &caps[2], 2, &value[..])) {
+            Some(data) => return data,
+            None => ()
+        };
+        match captures(&self.oct_int_pat, &value[..]).and_then(|caps| parse_int(&caps[1], &caps[2], 8, &value[..])) {
+            Some(data) => return data,
+            None => ()
+        };
+        match captures(&self.hex_int_pat, &value[..]).and_then(|caps| parse_int(&caps[1], &caps[2], 16, &value[..])) {
+            Some(data) => return data,
+            None => ()
+        };
+        match captures(&self.dec_int_pat, &value[..]).and_then(|caps| parse_int(&caps[1], &caps[2], 10, &value[..])) {
+            Some(data) => return data,
+            None => ()
+        };
+        match captures(&self.sexagesimal_int_pat, &value[..]) {
+            Some(caps) => return parse_sexagesimal_int(&caps[1], &caps[2], &value[..]),
+            None => ()
+        };
+
+        match captures(&self.flt_pat, &value[..]).and_then(|caps| parse_float(&caps[1], &caps[2])) {
+            Some(f) => return YamlStandardData::YamlFloat(f),
+            None => ()
+        };
+        match captures(&self.sexagesimal_flt_pat, &value[..]).and_then(|caps| parse_sexagesimal_float(&caps[1], &caps[2])) {
+            Some(f) => return YamlStandardData::YamlFloat(f),
+            None => ()
+        };
+
+        if is_match(&self.pos_inf_pat, &value[..]) {
+            YamlStandardData::YamlFloat(f64::INFINITY)
+        } else if is_match(&self.neg_inf_pat, &value[..]) {
+            YamlStandardData::YamlFloat(f64::NEG_INFINITY)
+        } else if is_match(&self.nan_pat, &value[..]) {
+            YamlStandardData::YamlFloat(f64::NAN)
+        } else if is_match(&self.null_pat, &value[..]) {
+            YamlStandardData::YamlNull
+        } else if is_match(&self.true_pat, &value[..]) {
+            YamlStandardData::YamlBool(true)
+        } else if is_match(&self.false_pat, &value[..]) {
+            YamlStandardData::YamlBool(false)
         } else {
-            1
+            match self.schema {
+                YamlSchema::Yaml11Schema | YamlSchema::YamlDefaultSchema => match self.resolve_timestamp(&value[..]) {
+                    Some(timestamp) => YamlStandardData::YamlTimestamp(timestamp),
+                    None => YamlStandardData::YamlString(value)
+                },
+                _ => YamlStandardData::YamlString(value)
+            }
+        }
+    }
+
+    fn resolve_timestamp(&self, value: &str) -> Option<YamlTimestamp> {
+        // a date on its own needs two-digit months and days, unlike the
+        // date part of a full timestamp
+        let caps = match self.date_pat.captures(value).or_else(|| self.timestamp_pat.captures(value)) {
+            Some(caps) => caps,
+            None => return None
+        };
+
+        let year: i32 = caps[1].parse().unwrap();
+        let month: u32 = caps[2].parse().unwrap();
+        let day: u32 = caps[3].parse().unwrap();
+        if month < 1 || month > 12 || day < 1 || day > days_in_month(year, month) {
+            return None;
+        }
+
+        let time = match caps.get(4) {
+            None => None,
+            Some(hour) => {
+                let hour: u32 = hour.as_str().parse().unwrap();
+                let minute: u32 = caps[5].parse().unwrap();
+                let second: u32 = caps[6].parse().unwrap();
+                // a leap second may show up as :60
+                if hour > 23 || minute > 59 || second > 60 {
+                    return None;
+                }
+
+                let nanosecond = match caps.get(7) {
+                    None => 0,
+                    Some(fraction) => {
+                        let digits: String = fraction.as_str().chars().chain("000000000".chars()).take(9).collect();
+                        digits.parse().unwrap()
+                    }
+                };
+
+                let offset = match caps.get(8) {
+                    None => None,
+                    Some(ref zone) if zone.as_str() == "Z" => Some(0),
+                    Some(_) => {
+                        let hours: i32 = caps[10].parse().unwrap();
+                        let minutes: i32 = caps.get(11).map_or(0, |m| m.as_str().parse().unwrap());
+                        if hours > 23 || minutes > 59 {
+                            return None;
+                        }
+                        let offset = hours * 60 + minutes;
+                        Some(if &caps[9] == "-" { -offset } else { offset })
+                    }
+                };
+
+                Some(YamlTime {
+                    hour: hour,
+                    minute: minute,
+                    second: second,
+                    nanosecond: nanosecond,
+                    offset: offset
+                })
+            }
         };
 
+        Some(YamlTimestamp {
+            year: year,
+            month: month,
+            day: day,
+            time: time
+        })
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 => if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 { 29 } else { 28 },
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+fn parse_int(sign: &str, data: &str, radix: u32, lexical: &str) -> Option<YamlStandardData> {
     let filtered:String = data.chars().filter(|&c| c != '_').collect();
-    let unsigned:isize = isize::from_str_radix(&filtered[..], radix).unwrap();
-    return unsigned * sign_flag;
+    match u64::from_str_radix(&filtered[..], radix) {
+        Ok(unsigned) => Some(signed_int(sign, unsigned, lexical)),
+        Err(ref e) if *e.kind() == IntErrorKind::PosOverflow => Some(YamlStandardData::YamlBigInteger(lexical.to_string())),
+        // only underscores, such as 0b_
+        Err(_) => None
+    }
 }
 
-fn parse_float(sign: &str, data: &str) -> f64 {
-    let unsigned:f64 = data.parse().unwrap();
+fn signed_int(sign: &str, unsigned: u64, lexical: &str) -> YamlStandardData {
     if sign == "-" {
-        return -unsigned;
+        if unsigned <= i64::MAX as u64 {
+            YamlStandardData::YamlInteger(-(unsigned as i64))
+        } else if unsigned == i64::MAX as u64 + 1 {
+            YamlStandardData::YamlInteger(i64::MIN)
+        } else {
+            YamlStandardData::YamlBigInteger(lexical.to_string())
+        }
+    } else if unsigned <= i64::MAX as u64 {
+        YamlStandardData::YamlInteger(unsigned as i64)
     } else {
-        return unsigned;
+        YamlStandardData::YamlUnsignedInteger(unsigned)
     }
 }
 
-impl YamlConstructor<YamlStandardData, YamlError> for YamlStandardConstructor {
-    fn construct_scalar(&self, scalar: document::YamlScalarData) -> Result<YamlStandardData, YamlError> {
-        let value = scalar.get_value();
-        let mark = scalar.start_mark();
+fn captures<'t>(pat: &Option<Regex>, value: &'t str) -> Option<Captures<'t>> {
+    match *pat {
+        Some(ref re) => re.captures(value),
+        None => None
+    }
+}
+
+fn is_match(pat: &Option<Regex>, value: &str) -> bool {
+    match *pat {
+        Some(ref re) => re.is_match(value),
+        None => false
+    }
+}
+
+// base 60 numbers such as 190:20:30
+fn parse_sexagesimal_int(sign: &str, data: &str, lexical: &str) -> YamlStandardData {
+    let mut unsigned: u64 = 0;
+    for part in data.split(':') {
+        let filtered: String = part.chars().filter(|&c| c != '_').collect();
+        let digit: u64 = match filtered.parse() {
+            Ok(n) => n,
+            Err(_) => return YamlStandardData::YamlBigInteger(lexical.to_string())
+        };
+        unsigned = match unsigned.checked_mul(60).and_then(|n| n.checked_add(digit)) {
+            Some(n) => n,
+            None => return YamlStandardData::YamlBigInteger(lexical.to_string())
+        };
+    }
+
+    signed_int(sign, unsigned, lexical)
+}
+
+fn parse_sexagesimal_float(sign: &str, data: &str) -> Option<f64> {
+    let mut unsigned = 0.0;
+    for part in data.split(':') {
+        let filtered: String = part.chars().filter(|&c| c != '_').collect();
+        match filtered.parse::<f64>() {
+            Ok(f) => unsigned = unsigned * 60.0 + f,
+            Err(_) => return None
+        }
+    }
+
+    if sign == "-" {
+        Some(-unsigned)
+    } else {
+        Some(unsigned)
+    }
+}
+
+fn parse_float_radix(sign: &str, data: &str, radix: u32) -> Option<f64> {
+    let filtered:String = data.chars().filter(|&c| c != '_').collect();
+    let unsigned = if radix == 10 {
+        match filtered.parse::<f64>() {
+            Ok(f) => f,
+            Err(_) => return None
+        }
+    } else {
+        let mut unsigned = 0.0;
+        for c in filtered.chars() {
+            match c.to_digit(radix) {
+                Some(d) => unsigned = unsigned * radix as f64 + d as f64,
+                None => return None
+            }
+        }
+        unsigned
+    };
+
+    if sign == "-" {
+        Some(-unsigned)
+    } else {
+        Some(unsigned)
+    }
+}
+
+// None when only underscores and a dot are left, such as ._
+fn parse_float(sign: &str, data: &str) -> Option<f64> {
+    let filtered:String = data.chars().filter(|&c| c != '_').collect();
+    let unsigned:f64 = match filtered.parse() {
+        Ok(f) => f,
+        Err(_) => return None
+    };
+    if sign == "-" {
+        return Some(-unsigned);
+    } else {
+        return Some(unsigned);
+    }
+}
+
+impl YamlConstructor<YamlStandardData, YamlError> for YamlStandardConstructor {
+    fn construct<'r>(&self, node: YamlNode<'r>) -> Result<YamlStandardData, YamlError> {
+        construct_tracked(&self.aliases, &self.limits, node, |node| match node {
+            YamlNode::YamlScalarNode(scalar) => self.construct_scalar(scalar),
+            YamlNode::YamlSequenceNode(sequence) => self.construct_sequence(sequence),
+            YamlNode::YamlMappingNode(mapping) => self.construct_mapping(mapping)
+        })
+    }
+
+    fn construct_scalar(&self, scalar: document::YamlScalarData) -> Result<YamlStandardData, YamlError> {
+        if let Some(handler) = self.tag_handler(&scalar) {
+            return (*handler)(self, YamlNode::YamlScalarNode(scalar));
+        }
+
+        // libyaml has already folded lines and processed escape sequences
+        let string = scalar.get_value();
+        let mark = scalar.start_mark();
+        let plain = scalar.style() == YamlScalarStyle::YamlPlainScalarStyle;
+
+        if !scalar.is_tag_explicit() {
+            return Ok(if plain {
+                self.resolve_plain_scalar(string)
+            } else {
+                YamlStandardData::YamlString(string)
+            });
+        }
+
+        self.resolve_explicit_scalar(scalar.tag().unwrap(), string, plain, &mark)
+    }
+
+    fn construct_sequence(&self, sequence: document::YamlSequenceData) -> Result<YamlStandardData, YamlError> {
+        if let Some(handler) = self.tag_handler(&sequence) {
+            return (*handler)(self, YamlNode::YamlSequenceNode(sequence));
+        }
+
+        match sequence.tag() {
+            Some(ref tag) if &tag[..] == OMAP_TAG || &tag[..] == PAIRS_TAG =>
+                return self.construct_pairs(sequence, &tag[..] == OMAP_TAG),
+            Some(ref tag) if is_core_tag(tag) && &tag[..] != SEQ_TAG =>
+                return Err(tag_mismatch_error(tag, &sequence.start_mark())),
+            Some(ref tag) if sequence.is_tag_explicit() && &tag[..] != SEQ_TAG => {
+                let data = try!(self.construct_untagged_sequence(sequence));
+                return Ok(YamlStandardData::YamlTagged(tag.clone(), Box::new(data)));
+            },
+            _ => ()
+        }
+
+        self.construct_untagged_sequence(sequence)
+    }
+
+    fn construct_mapping(&self, mapping: document::YamlMappingData) -> Result<YamlStandardData, YamlError> {
+        if let Some(handler) = self.tag_handler(&mapping) {
+            return (*handler)(self, YamlNode::YamlMappingNode(mapping));
+        }
+
+        match mapping.tag() {
+            Some(ref tag) if &tag[..] == SET_TAG =>
+                return self.construct_set(mapping),
+            Some(ref tag) if is_core_tag(tag) && &tag[..] != MAP_TAG =>
+                return Err(tag_mismatch_error(tag, &mapping.start_mark())),
+            Some(ref tag) if mapping.is_tag_explicit() && &tag[..] != MAP_TAG => {
+                let data = try!(self.construct_untagged_mapping(mapping));
+                return Ok(YamlStandardData::YamlTagged(tag.clone(), Box::new(data)));
+            },
+            _ => ()
+        }
+
+        self.construct_untagged_mapping(mapping)
+    }
+}
+
+// A node of the standard tree annotated with its position in the source
+#[derive(PartialEq, Clone, Debug)]
+pub struct YamlSpanned {
+    pub data: YamlSpannedData,
+    pub start: YamlMark,
+    pub end: YamlMark,
+    // the tag written in the source, if any
+    pub tag: Option<String>,
+    // None for sequences and mappings
+    pub style: Option<YamlScalarStyle>,
+}
+
+#[derive(Clone, Debug)]
+pub enum YamlSpannedData {
+    // scalars, and collections tagged with anything but !!seq or !!map, which
+    // are constructed as a whole by the standard constructor
+    YamlValue(YamlStandardData),
+    YamlSequence(Vec<YamlSpanned>),
+    YamlMapping(Vec<(YamlSpanned, YamlSpanned)>),
+    // the data of an aliased node, shared by every reference to it. It
+    // compares equal to the data it holds.
+    YamlShared(Arc<YamlSpannedData>),
+}
+
+impl YamlSpannedData {
+    // the data itself, for shared data
+    pub fn resolved(&self) -> &YamlSpannedData {
+        match *self {
+            YamlSpannedData::YamlShared(ref data) => data.resolved(),
+            _ => self
+        }
+    }
+
+    fn into_unshared(self) -> YamlSpannedData {
+        match self {
+            YamlSpannedData::YamlShared(data) => data.resolved().clone(),
+            data => data
+        }
+    }
+
+    fn into_standard(self, shared: &mut HashMap<*const YamlSpannedData, (Arc<YamlSpannedData>, YamlStandardData)>) -> YamlStandardData {
+        match self {
+            YamlSpannedData::YamlValue(data) => data,
+            YamlSpannedData::YamlSequence(items) =>
+                YamlStandardData::YamlSequence(items.into_iter().map(|item| item.data.into_standard(shared)).collect()),
+            YamlSpannedData::YamlMapping(pairs) =>
+                YamlStandardData::YamlMapping(pairs.into_iter().map(|(k, v)| (k.data.into_standard(shared), v.data.into_standard(shared))).collect()),
+            YamlSpannedData::YamlShared(data) => shared_to_standard(data, shared)
+        }
+    }
+}
+
+// shared data is converted once, and stays shared
+fn shared_to_standard(data: Arc<YamlSpannedData>,
+        shared: &mut HashMap<*const YamlSpannedData, (Arc<YamlSpannedData>, YamlStandardData)>) -> YamlStandardData {
+    let key = &*data as *const YamlSpannedData;
+    if let Some(&(_, ref value)) = shared.get(&key) {
+        return value.clone();
+    }
+    let value = data.resolved().to_standard(shared).share();
+    // the entry keeps the data alive, so that its address isn't reused
+    shared.insert(key, (data, value.clone()));
+    value
+}
+
+impl YamlSpannedData {
+    // like into_standard, without copying anything but the converted values
+    fn to_standard(&self, shared: &mut HashMap<*const YamlSpannedData, (Arc<YamlSpannedData>, YamlStandardData)>) -> YamlStandardData {
+        match *self {
+            YamlSpannedData::YamlValue(ref data) => data.clone(),
+            YamlSpannedData::YamlSequence(ref items) =>
+                YamlStandardData::YamlSequence(items.iter().map(|item| item.data.to_standard(shared)).collect()),
+            YamlSpannedData::YamlMapping(ref pairs) =>
+                YamlStandardData::YamlMapping(pairs.iter().map(|&(ref k, ref v)| (k.data.to_standard(shared), v.data.to_standard(shared))).collect()),
+            YamlSpannedData::YamlShared(ref data) => shared_to_standard(data.clone(), shared)
+        }
+    }
+}
+
+impl PartialEq for YamlSpannedData {
+    fn eq(&self, other: &YamlSpannedData) -> bool {
+        match (self.resolved(), other.resolved()) {
+            (&YamlSpannedData::YamlValue(ref a), &YamlSpannedData::YamlValue(ref b)) => a == b,
+            (&YamlSpannedData::YamlSequence(ref a), &YamlSpannedData::YamlSequence(ref b)) => a == b,
+            (&YamlSpannedData::YamlMapping(ref a), &YamlSpannedData::YamlMapping(ref b)) => a == b,
+            _ => false
+        }
+    }
+}
+
+impl YamlShare for YamlSpanned {
+    fn share(self) -> YamlSpanned {
+        let data = match self.data {
+            YamlSpannedData::YamlShared(data) => YamlSpannedData::YamlShared(data),
+            data => YamlSpannedData::YamlShared(Arc::new(data))
+        };
+        YamlSpanned { data: data, ..self }
+    }
+}
+
+impl YamlSpanned {
+    pub fn into_standard(self) -> YamlStandardData {
+        self.data.into_standard(&mut HashMap::new())
+    }
+
+    pub fn to_standard(&self) -> YamlStandardData {
+        self.data.to_standard(&mut HashMap::new())
+    }
+}
+
+impl From<YamlSpanned> for YamlStandardData {
+    fn from(spanned: YamlSpanned) -> YamlStandardData {
+        spanned.into_standard()
+    }
+}
+
+fn node_span<N: YamlNodeData>(node: &N) -> (YamlMark, YamlMark, Option<String>) {
+    let tag = if node.is_tag_explicit() { node.tag() } else { None };
+    (node.start_mark(), node.end_mark(), tag)
+}
+
+#[derive(Clone)]
+pub struct YamlSpannedConstructor {
+    ctor: YamlStandardConstructor,
+    aliases: RefCell<YamlAliasTracker<YamlSpanned>>
+}
+
+impl YamlSpannedConstructor {
+    pub fn new() -> YamlSpannedConstructor {
+        YamlSpannedConstructor::with_constructor(YamlStandardConstructor::new())
+    }
+
+    // the schema, tag handlers and merge key setting of ctor all apply
+    pub fn with_constructor(ctor: YamlStandardConstructor) -> YamlSpannedConstructor {
+        YamlSpannedConstructor {
+            ctor: ctor,
+            aliases: RefCell::new(YamlAliasTracker::new())
+        }
+    }
+
+    fn keeps_spans<N: YamlNodeData>(&self, node: &N, default_tag: &str) -> bool {
+        if !node.is_tag_explicit() {
+            return true;
+        }
+        node.tag().map_or(false, |tag| &tag[..] == default_tag && !self.ctor.tag_handlers.contains_key(&tag))
+    }
+
+    fn construct_merge(&self, node: YamlNode, merged: &mut Vec<Vec<(YamlSpanned, YamlSpanned)>>) -> Result<(), YamlError> {
+        let mark = node_start_mark(&node);
+        let error = || standard_error("expected a mapping or list of mappings for merging".to_string(), &mark);
+
+        match try!(self.construct(node)).data.into_unshared() {
+            YamlSpannedData::YamlMapping(pairs) => merged.push(pairs),
+            YamlSpannedData::YamlSequence(items) => for item in items.into_iter() {
+                match item.data.into_unshared() {
+                    YamlSpannedData::YamlMapping(pairs) => merged.push(pairs),
+                    _ => return Err(error())
+                }
+            },
+            _ => return Err(error())
+        }
+
+        Ok(())
+    }
+}
+
+impl YamlConstructor<YamlSpanned, YamlError> for YamlSpannedConstructor {
+    fn construct<'r>(&self, node: YamlNode<'r>) -> Result<YamlSpanned, YamlError> {
+        construct_tracked(&self.aliases, &self.ctor.limits, node, |node| match node {
+            YamlNode::YamlScalarNode(scalar) => self.construct_scalar(scalar),
+            YamlNode::YamlSequenceNode(sequence) => self.construct_sequence(sequence),
+            YamlNode::YamlMappingNode(mapping) => self.construct_mapping(mapping)
+        })
+    }
+
+    fn construct_scalar(&self, scalar: document::YamlScalarData) -> Result<YamlSpanned, YamlError> {
+        let (start, end, tag) = node_span(&scalar);
+        let style = scalar.style();
+        let data = try!(self.ctor.construct_scalar(scalar));
+
+        Ok(YamlSpanned {
+            data: YamlSpannedData::YamlValue(data),
+            start: start,
+            end: end,
+            tag: tag,
+            style: Some(style)
+        })
+    }
+
+    fn construct_sequence(&self, sequence: document::YamlSequenceData) -> Result<YamlSpanned, YamlError> {
+        let (start, end, tag) = node_span(&sequence);
+        let data = if self.keeps_spans(&sequence, SEQ_TAG) {
+            let res:Result<Vec<YamlSpanned>, YamlError> = sequence.values().map(|node| { self.construct(node) }).collect();
+            YamlSpannedData::YamlSequence(try!(res))
+        } else {
+            YamlSpannedData::YamlValue(try!(self.ctor.construct_sequence(sequence)))
+        };
+
+        Ok(YamlSpanned {
+            data: data,
+            start: start,
+            end: end,
+            tag: tag,
+            style: None
+        })
+    }
+
+    fn construct_mapping(&self, mapping: document::YamlMappingData) -> Result<YamlSpanned, YamlError> {
+        let (start, end, tag) = node_span(&mapping);
+        let spanned = |data| YamlSpanned {
+            data: data,
+            start: start,
+            end: end,
+            tag: tag.clone(),
+            style: None
+        };
+
+        if !self.keeps_spans(&mapping, MAP_TAG) {
+            let data = try!(self.ctor.construct_mapping(mapping));
+            return Ok(spanned(YamlSpannedData::YamlValue(data)));
+        }
+
+        // keys are compared by their standard values, which are converted
+        // only once, and only when they are compared at all
+        let mut pairs: Vec<(YamlSpanned, YamlSpanned)> = Vec::new();
+        let mut standard_keys = Vec::new();
+        let mut keys = YamlKeyIndex::new();
+        let mut merged = Vec::new();
+        for (key_node, value_node) in mapping.pairs() {
+            if self.ctor.is_merge_key(&key_node) {
+                try!(self.construct_merge(value_node, &mut merged));
+            } else {
+                let key = try!(self.construct(key_node));
+                if self.ctor.unique_keys {
+                    let standard_key = key.to_standard();
+                    let hash = key_hash(&standard_key);
+                    if let Some(i) = keys.find(hash, |i| standard_keys[i] == standard_key) {
+                        return Err(duplicate_key_error(&pairs[i].0.start, &key.start));
+                    }
+                    keys.insert(hash, standard_keys.len());
+                    standard_keys.push(standard_key);
+                }
+                let value = try!(self.construct(value_node));
+                pairs.push((key, value));
+            }
+        }
+
+        if merged.is_empty() {
+            return Ok(spanned(YamlSpannedData::YamlMapping(pairs)));
+        }
+
+        // same precedence as YamlStandardConstructor; merged pairs keep the
+        // positions of the mapping they were copied from
+        if !self.ctor.unique_keys {
+            for &(ref key, _) in pairs.iter() {
+                let standard_key = key.to_standard();
+                keys.insert(key_hash(&standard_key), standard_keys.len());
+                standard_keys.push(standard_key);
+            }
+        }
+        let mut res: Vec<(YamlSpanned, YamlSpanned)> = Vec::new();
+        for (key, value) in merged.into_iter().flat_map(|p| p.into_iter()) {
+            let standard_key = key.to_standard();
+            let hash = key_hash(&standard_key);
+            if keys.find(hash, |i| standard_keys[i] == standard_key).is_none() {
+                keys.insert(hash, standard_keys.len());
+                standard_keys.push(standard_key);
+                res.push((key, value));
+            }
+        }
+        res.extend(pairs.into_iter());
+        Ok(spanned(YamlSpannedData::YamlMapping(res)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::YamlStandardData::*;
+    use parser::{YamlParser, YamlByteParser};
+    use std::{f64, i64, u64};
+    use ffi::YamlEncoding::YamlUtf8Encoding;
+    use constructor::{YamlConstructor, YamlStandardConstructor, YamlSchema, YamlStandardData, YamlTimestamp, YamlTime};
+    use constructor::{YamlSpannedConstructor, YamlSpanned, YamlSpannedData, YamlConstructorOptions};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use parser::YamlLimits;
+    use error::YamlMark;
+    use ffi::YamlScalarStyle;
+    use document::YamlNode;
+    use error::YamlError;
+    use ffi::YamlErrorType;
+
+    #[test]
+    fn test_standard_constructor() {
+        let data = "[1, 2, 3]";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+
+        match parser.load().next() {
+            Some(Ok(doc)) => {
+                let ctor = YamlStandardConstructor::new();
+                assert_eq!(Ok(YamlSequence(vec![YamlInteger(1), YamlInteger(2), YamlInteger(3)])), ctor.construct(doc.root().unwrap()))
+            },
+            _ => panic!("unexpected result")
+        }
+    }
+
+    #[test]
+    fn test_integer_parser() {
+        let data = "[0o10, 0x21, -30]";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+
+        match parser.load().next() {
+            Some(Ok(doc)) => {
+                let ctor = YamlStandardConstructor::new();
+                assert_eq!(Ok(YamlSequence(vec![YamlInteger(0o10), YamlInteger(0x21), YamlInteger(-30)])), ctor.construct(doc.root().unwrap()))
+            },
+            _ => panic!("unexpected result")
+        }
+    }
+
+    #[test]
+    fn test_float_parser() {
+        let data = "[0.3, -.4, 1e+2, -1.2e-3]";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+
+        match parser.load().next() {
+            Some(Ok(doc)) => {
+                let ctor = YamlStandardConstructor::new();
+                let value = ctor.construct(doc.root().unwrap());
+                match value {
+                    Ok(YamlSequence(seq)) => {
+                        match &seq[..] {
+                            &[YamlFloat(f1), YamlFloat(f2), YamlFloat(f3), YamlFloat(f4)] => {
+                                assert!((f1 - 0.3).abs() < 1.0e-6);
+                                assert!((f2 + 0.4) < 1.0e-6);
+                                assert!((f3 - 1e+2) < 1.0e-6);
+                                assert!((f4 + 1.2e-3) < 1.0e-6);
+                            },
+                            _ => panic!("unexpected sequence")
+                        }
+                    },
+                    _ => panic!("unexpected result")
+                }
+            },
+            _ => panic!("document parse failure")
+        }
+    }
+
+    #[test]
+    fn test_inf_parser() {
+        let data = "[.inf, -.INF]";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+
+        match parser.load().next() {
+            Some(Ok(doc)) => {
+                let ctor = YamlStandardConstructor::new();
+                assert_eq!(Ok(YamlSequence(vec![YamlFloat(f64::INFINITY), YamlFloat(f64::NEG_INFINITY)])), ctor.construct(doc.root().unwrap()))
+            },
+            _ => panic!("document parse failure")
+        }
+    }
+
+    #[test]
+    fn test_misc_parser() {
+        let data = "[yes, False, ~]";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+
+        match parser.load().next() {
+            Some(Ok(doc)) => {
+                let ctor = YamlStandardConstructor::new();
+                assert_eq!(Ok(YamlSequence(vec![YamlBool(true), YamlBool(false), YamlNull])), ctor.construct(doc.root().unwrap()))
+            },
+            _ => panic!("document parse failure")
+        }
+    }
+
+    #[test]
+    fn test_double_quoted_parser() {
+        let data = r#""hello, \"world\"""#;
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+
+        match parser.load().next() {
+            Some(Ok(doc)) => {
+                let ctor = YamlStandardConstructor::new();
+                assert_eq!(Ok(YamlString("hello, \"world\"".to_string())), ctor.construct(doc.root().unwrap()))
+            },
+            _ => panic!("document parse failure")
+        }
+    }
+
+    #[test]
+    fn test_double_quoted_escapes() {
+        let data = "[\"C:\\\\new\", \"\\\\x\", \"\\x41\\u00e9\\t\", \"a\\\n  b\"]";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+
+        match parser.load().next() {
+            Some(Ok(doc)) => {
+                let ctor = YamlStandardConstructor::new();
+                assert_eq!(Ok(YamlSequence(vec![YamlString("C:\\new".to_string()), YamlString("\\x".to_string()),
+                    YamlString("A\u{e9}\t".to_string()), YamlString("ab".to_string())])), ctor.construct(doc.root().unwrap()))
+            },
+            _ => panic!("document parse failure")
+        }
+    }
+
+    #[test]
+    fn test_single_quoted_parser() {
+        let data = r#"'here''s to "quotes"'"#;
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+
+        match parser.load().next() {
+            Some(Ok(doc)) => {
+                let ctor = YamlStandardConstructor::new();
+                assert_eq!(Ok(YamlString(r#"here's to "quotes""#.to_string())), ctor.construct(doc.root().unwrap()))
+            },
+            _ => panic!("document parse failure")
+        }
+    }
+
+    #[test]
+    fn test_underlined_integer() {
+        let data = "[1_000, -2_000_000]";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+
+        match parser.load().next() {
+            Some(Ok(doc)) => {
+                let ctor = YamlStandardConstructor::new();
+                assert_eq!(Ok(YamlSequence(vec![YamlInteger(1000), YamlInteger(-2000000)])), ctor.construct(doc.root().unwrap()))
+            },
+            _ => panic!("document parse failure")
+        }
+    }
+
+    #[test]
+    fn test_negative_radix() {
+        let data = "[-0x30, -0700, -0b110]";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+
+        match parser.load().next() {
+            Some(Ok(doc)) => {
+                let ctor = YamlStandardConstructor::new();
+                assert_eq!(Ok(YamlSequence(vec![YamlInteger(-48), YamlInteger(-448), YamlInteger(-6)])), ctor.construct(doc.root().unwrap()))
+            },
+            _ => panic!("document parse failure")
+        }
+    }
+
+    #[test]
+    fn test_explicit_tags() {
+        let data = "[!!str 123, !!int \"42\", !!float '1', !!bool \"yes\", !!null '', ! 12, &a 7, *a, !!str &b 0x10]";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+
+        match parser.load().next() {
+            Some(Ok(doc)) => {
+                let ctor = YamlStandardConstructor::new();
+                assert_eq!(Ok(YamlSequence(vec![YamlString("123".to_string()), YamlInteger(42), YamlFloat(1.0),
+                    YamlBool(true), YamlNull, YamlString("12".to_string()), YamlInteger(7), YamlInteger(7),
+                    YamlString("0x10".to_string())])), ctor.construct(doc.root().unwrap()))
+            },
+            _ => panic!("document parse failure")
+        }
+    }
+
+    #[test]
+    fn test_tag_mismatch() {
+        let data = "a: 1\nb: !!int abc";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+
+        match parser.load().next() {
+            Some(Ok(doc)) => {
+                let ctor = YamlStandardConstructor::new();
+                let err = ctor.construct(doc.root().unwrap()).unwrap_err();
+                let mark = err.context.unwrap().problem_mark;
+                assert_eq!((1, 3), (mark.line, mark.column));
+            },
+            _ => panic!("document parse failure")
+        }
+    }
+
+    #[test]
+    fn test_collection_tag_mismatch() {
+        let data = "- !!map [1]";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+
+        match parser.load().next() {
+            Some(Ok(doc)) => {
+                let ctor = YamlStandardConstructor::new();
+                assert!(ctor.construct(doc.root().unwrap()).is_err());
+            },
+            _ => panic!("document parse failure")
+        }
+    }
 
-        match scalar.style() {
-            YamlScalarStyle::YamlPlainScalarStyle => {
-                match self.bin_int_pat.captures(&value[..]) {
-                    Some(caps) => return Ok(YamlStandardData::YamlInteger(parse_int(
-                                &caps[1], &caps[2], 2))),
-                    None => ()
-                };
-                match self.oct_int_pat.captures(&value[..]) {
-                    Some(caps) => return Ok(YamlStandardData::YamlInteger(parse_int(
-                                &caps[1], &caps[2], 8))),
-                    None => ()
-                };
-                match self.hex_int_pat.captures(&value[..]) {
-                    Some(caps) => return Ok(YamlStandardData::YamlInteger(parse_int(
-                                &caps[1], &caps[2], 16))),
-                    None => ()
-                };
+    #[test]
+    fn test_timestamp_parser() {
+        let data = "[2001-12-14t21:59:43.10-05:00, 2002-12-14, 2001-12-14 21:59:43.10 -5, 2001-12-15T02:59:43.1Z, \
+                     2001-12-15 2:59:43, !!timestamp '2001-12-14', 2001-02-30, 2001-1-2, 2001-1-2 3:04:05]";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
 
-                if self.dec_int_pat.is_match(&value[..]) {
-                    return Ok(YamlStandardData::YamlInteger(parse_int("", &value[..], 10)));
-                }
+        let time = |hour, offset| Some(YamlTime { hour: hour, minute: 59, second: 43, nanosecond: 100000000, offset: offset });
+        match parser.load().next() {
+            Some(Ok(doc)) => {
+                let ctor = YamlStandardConstructor::new();
+                assert_eq!(Ok(YamlSequence(vec![
+                    YamlTimestamp(YamlTimestamp { year: 2001, month: 12, day: 14, time: time(21, Some(-300)) }),
+                    YamlTimestamp(YamlTimestamp { year: 2002, month: 12, day: 14, time: None }),
+                    YamlTimestamp(YamlTimestamp { year: 2001, month: 12, day: 14, time: time(21, Some(-300)) }),
+                    YamlTimestamp(YamlTimestamp { year: 2001, month: 12, day: 15, time: time(2, Some(0)) }),
+                    YamlTimestamp(YamlTimestamp { year: 2001, month: 12, day: 15,
+                        time: Some(YamlTime { hour: 2, minute: 59, second: 43, nanosecond: 0, offset: None }) }),
+                    YamlTimestamp(YamlTimestamp { year: 2001, month: 12, day: 14, time: None }),
+                    YamlString("2001-02-30".to_string()),
+                    YamlString("2001-1-2".to_string()),
+                    YamlTimestamp(YamlTimestamp { year: 2001, month: 1, day: 2,
+                        time: Some(YamlTime { hour: 3, minute: 4, second: 5, nanosecond: 0, offset: None }) }),
+                ])), ctor.construct(doc.root().unwrap()))
+            },
+            _ => panic!("document parse failure")
+        }
+    }
 
-                match self.flt_pat.captures(&value[..]) {
-                    Some(caps) => return Ok(YamlStandardData::YamlFloat(parse_float(
-                                &caps[1], &caps[2]))),
-                    None => ()
-                };
+    #[test]
+    fn test_timestamp_tag_mismatch() {
+        let data = "!!timestamp 2001-13-01";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
 
-                if self.pos_inf_pat.is_match(&value[..]) {
-                    Ok(YamlStandardData::YamlFloat(f64::INFINITY))
-                } else if self.neg_inf_pat.is_match(&value[..]) {
-                    Ok(YamlStandardData::YamlFloat(f64::NEG_INFINITY))
-                } else if self.nan_pat.is_match(&value[..]) {
-                    Ok(YamlStandardData::YamlFloat(f64::NAN))
-                } else if self.null_pat.is_match(&value[..]) {
-                    Ok(YamlStandardData::YamlNull)
-                } else if self.true_pat.is_match(&value[..]) {
-                    Ok(YamlStandardData::YamlBool(true))
-                } else if self.false_pat.is_match(&value[..]) {
-                    Ok(YamlStandardData::YamlBool(false))
-                } else {
-                    Ok(YamlStandardData::YamlString(value))
-                }
-            },
-            YamlScalarStyle::YamlDoubleQuotedScalarStyle => {
-                YamlStandardConstructor::parse_double_quoted(&value[..], &mark).map(YamlStandardData::YamlString)
+        match parser.load().next() {
+            Some(Ok(doc)) => {
+                let ctor = YamlStandardConstructor::new();
+                assert!(ctor.construct(doc.root().unwrap()).is_err());
             },
-            _ => {
-                Ok(YamlStandardData::YamlString(value))
-            }
+            _ => panic!("document parse failure")
         }
     }
 
-    fn construct_sequence(&self, sequence: document::YamlSequenceData) -> Result<YamlStandardData, YamlError> {
-        let res:Result<Vec<YamlStandardData>, YamlError> = sequence.values().map(|node| { self.construct(node) }).collect();
-        res.map(|list| YamlStandardData::YamlSequence(list))
-    }
+    #[test]
+    fn test_timestamp_display() {
+        let date = YamlTimestamp { year: 2002, month: 1, day: 2, time: None };
+        assert_eq!("2002-01-02", date.to_string());
 
-    fn construct_mapping(&self, mapping: document::YamlMappingData) -> Result<YamlStandardData, YamlError> {
-        let pairs = mapping.pairs().map(|(key_node, value_node)| {
-            match self.construct(key_node) {
-                Ok(key) => match self.construct(value_node) {
-                    Ok(value) => Ok((key, value)),
-                    Err(e) => Err(e)
-                },
-                Err(e) => Err(e)
-            }
-        });
-        let res:Result<Vec<(YamlStandardData, YamlStandardData)>, YamlError> = pairs.collect();
-        res.map(YamlStandardData::YamlMapping)
+        let time = YamlTimestamp { year: 2001, month: 12, day: 14,
+            time: Some(YamlTime { hour: 21, minute: 59, second: 43, nanosecond: 100000000, offset: Some(-330) }) };
+        assert_eq!("2001-12-14T21:59:43.1-05:30", time.to_string());
     }
-}
-
-#[cfg(test)]
-mod test {
-    use super::YamlStandardData::*;
-    use parser::{YamlParser, YamlByteParser};
-    use std::f64;
-    use ffi::YamlEncoding::YamlUtf8Encoding;
-    use constructor::{YamlConstructor, YamlStandardConstructor};
 
     #[test]
-    fn test_standard_constructor() {
-        let data = "[1, 2, 3]";
+    fn test_binary_parser() {
+        let data = "picture: !!binary |\n  R0lGODlhDAAMAIQAAP//9/X\n  17unp5WZmZgAAAOfn515eXv\n";
         let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
 
         match parser.load().next() {
             Some(Ok(doc)) => {
                 let ctor = YamlStandardConstructor::new();
-                assert_eq!(Ok(YamlSequence(vec![YamlInteger(1), YamlInteger(2), YamlInteger(3)])), ctor.construct(doc.root().unwrap()))
+                let expected = b"GIF89a\x0c\x00\x0c\x00\x84\x00\x00\xff\xff\xf7\xf5\xf5\xee\xe9\xe9\xe5fff\x00\x00\x00\xe7\xe7\xe7^^^";
+                assert_eq!(Ok(YamlMapping(vec![(YamlString("picture".to_string()), YamlBinary(expected.to_vec()))])),
+                    ctor.construct(doc.root().unwrap()))
             },
-            _ => panic!("unexpected result")
+            _ => panic!("document parse failure")
         }
     }
 
     #[test]
-    fn test_integer_parser() {
-        let data = "[0o10, 0x21, -30]";
+    fn test_invalid_binary() {
+        let data = "!!binary 'not base64'";
         let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
 
         match parser.load().next() {
             Some(Ok(doc)) => {
                 let ctor = YamlStandardConstructor::new();
-                assert_eq!(Ok(YamlSequence(vec![YamlInteger(0o10), YamlInteger(0x21), YamlInteger(-30)])), ctor.construct(doc.root().unwrap()))
+                assert!(ctor.construct(doc.root().unwrap()).is_err());
             },
-            _ => panic!("unexpected result")
+            _ => panic!("document parse failure")
         }
     }
 
     #[test]
-    fn test_float_parser() {
-        let data = "[0.3, -.4, 1e+2, -1.2e-3]";
+    fn test_merge_keys() {
+        let data = "
+- &CENTER { x: 1, y: 2 }
+- &LEFT { x: 0, y: 2 }
+- &BIG { r: 10 }
+- &SMALL { r: 1 }
+- << : *CENTER
+  r: 10
+- << : [ *CENTER, *BIG ]
+  label: center/big
+- << : [ *BIG, *LEFT, *SMALL ]
+  x: 1
+";
         let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
 
         match parser.load().next() {
             Some(Ok(doc)) => {
                 let ctor = YamlStandardConstructor::new();
-                let value = ctor.construct(doc.root().unwrap());
-                match value {
-                    Ok(YamlSequence(seq)) => {
-                        match &seq[..] {
-                            &[YamlFloat(f1), YamlFloat(f2), YamlFloat(f3), YamlFloat(f4)] => {
-                                assert!((f1 - 0.3).abs() < 1.0e-6);
-                                assert!((f2 + 0.4) < 1.0e-6);
-                                assert!((f3 - 1e+2) < 1.0e-6);
-                                assert!((f4 + 1.2e-3) < 1.0e-6);
-                            },
-                            _ => panic!("unexpected sequence")
-                        }
+                let key = |k: &str| YamlString(k.to_string());
+                match ctor.construct(doc.root().unwrap()) {
+                    Ok(YamlSequence(items)) => {
+                        assert_eq!(YamlMapping(vec![(key("x"), YamlInteger(1)), (key("y"), YamlInteger(2)), (key("r"), YamlInteger(10))]), items[4]);
+                        assert_eq!(YamlMapping(vec![(key("x"), YamlInteger(1)), (key("y"), YamlInteger(2)), (key("r"), YamlInteger(10)),
+                            (key("label"), key("center/big"))]), items[5]);
+                        assert_eq!(YamlMapping(vec![(key("r"), YamlInteger(10)), (key("y"), YamlInteger(2)), (key("x"), YamlInteger(1))]), items[6]);
                     },
-                    _ => panic!("unexpected result")
+                    res => panic!("unexpected result: {:?}", res)
                 }
             },
             _ => panic!("document parse failure")
@@ -329,86 +1753,604 @@ mod test {
     }
 
     #[test]
-    fn test_inf_parser() {
-        let data = "[.inf, -.INF]";
+    fn test_large_merge() {
+        let keys: Vec<String> = (0..5000).map(|i| format!("k{}: {}", i, i)).collect();
+        let data = format!("- &a {{{}}}\n- {{<<: [*a, *a], k1: x, k5000: y}}\n", keys.join(", "));
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let doc = parser.load().next().unwrap().unwrap();
+
+        match YamlStandardConstructor::new().construct(doc.root().unwrap()) {
+            Ok(YamlSequence(ref items)) => match items[1] {
+                YamlMapping(ref pairs) => {
+                    assert_eq!(5001, pairs.len());
+                    assert_eq!((YamlString("k0".to_string()), YamlInteger(0)), pairs[0]);
+                    assert_eq!((YamlString("k1".to_string()), YamlString("x".to_string())), pairs[4999]);
+                },
+                ref other => panic!("unexpected value: {:?}", other)
+            },
+            res => panic!("unexpected result: {:?}", res)
+        }
+    }
+
+    #[test]
+    fn test_merge_keys_disabled() {
+        let data = "base: &b {a: 1}\nderived:\n  <<: *b\n";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+
+        match parser.load().next() {
+            Some(Ok(doc)) => {
+                let mut ctor = YamlStandardConstructor::new();
+                ctor.set_merge_keys(false);
+                let base = YamlMapping(vec![(YamlString("a".to_string()), YamlInteger(1))]);
+                assert_eq!(Ok(YamlMapping(vec![
+                    (YamlString("base".to_string()), base.clone()),
+                    (YamlString("derived".to_string()), YamlMapping(vec![(YamlString("<<".to_string()), base)]))
+                ])), ctor.construct(doc.root().unwrap()))
+            },
+            _ => panic!("document parse failure")
+        }
+    }
+
+    #[test]
+    fn test_invalid_merge() {
+        let data = "<<: [{a: 1}, 2]";
         let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
 
         match parser.load().next() {
             Some(Ok(doc)) => {
                 let ctor = YamlStandardConstructor::new();
-                assert_eq!(Ok(YamlSequence(vec![YamlFloat(f64::INFINITY), YamlFloat(f64::NEG_INFINITY)])), ctor.construct(doc.root().unwrap()))
+                assert!(ctor.construct(doc.root().unwrap()).is_err());
             },
             _ => panic!("document parse failure")
         }
     }
 
     #[test]
-    fn test_misc_parser() {
-        let data = "[yes, False, ~]";
+    fn test_typed_collections() {
+        let data = "
+set: !!set
+  ? a
+  ? b
+omap: !!omap
+  - b: 1
+  - a: 2
+pairs: !!pairs [a: 1, a: 2]
+";
         let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
 
         match parser.load().next() {
             Some(Ok(doc)) => {
                 let ctor = YamlStandardConstructor::new();
-                assert_eq!(Ok(YamlSequence(vec![YamlBool(true), YamlBool(false), YamlNull])), ctor.construct(doc.root().unwrap()))
+                let key = |k: &str| YamlString(k.to_string());
+                assert_eq!(Ok(YamlMapping(vec![
+                    (key("set"), YamlSet(vec![key("a"), key("b")])),
+                    (key("omap"), YamlOrderedMap(vec![(key("b"), YamlInteger(1)), (key("a"), YamlInteger(2))])),
+                    (key("pairs"), YamlPairs(vec![(key("a"), YamlInteger(1)), (key("a"), YamlInteger(2))])),
+                ])), ctor.construct(doc.root().unwrap()))
             },
             _ => panic!("document parse failure")
         }
     }
 
     #[test]
-    fn test_double_quoted_parser() {
-        let data = r#""hello, \"world\"""#;
+    fn test_invalid_typed_collections() {
+        let ctor = YamlStandardConstructor::new();
+        for data in ["!!set {a: 1}", "!!set [a]", "!!omap [a: 1, a: 2]", "!!omap [{a: 1, b: 2}]", "!!pairs [a]"].iter() {
+            let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+            match parser.load().next() {
+                Some(Ok(doc)) => assert!(ctor.construct(doc.root().unwrap()).is_err(), "{} was accepted", data),
+                _ => panic!("document parse failure")
+            }
+        }
+    }
+
+    #[test]
+    fn test_duplicate_set_key_mark() {
+        let data = "--- !!set\n? a\n? b\n? 0x1\n? 1\n";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let doc = parser.load().next().unwrap().unwrap();
+
+        let err = YamlStandardConstructor::new().construct(doc.root().unwrap()).unwrap_err();
+        assert_eq!(Some("found duplicate key in set".to_string()), err.problem);
+        let mark = err.context.unwrap().problem_mark;
+        assert_eq!((4, 2), (mark.line, mark.column));
+    }
+
+    #[test]
+    fn test_integer_range() {
+        let data = "[9223372036854775807, -9223372036854775808, 18446744073709551615, 0xffff_ffff_ffff_ffff, \
+                     99999999999999999999, -9223372036854775809]";
         let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
 
         match parser.load().next() {
             Some(Ok(doc)) => {
                 let ctor = YamlStandardConstructor::new();
-                assert_eq!(Ok(YamlString("hello, \"world\"".to_string())), ctor.construct(doc.root().unwrap()))
+                assert_eq!(Ok(YamlSequence(vec![
+                    YamlInteger(i64::MAX), YamlInteger(i64::MIN),
+                    YamlUnsignedInteger(u64::MAX), YamlUnsignedInteger(u64::MAX),
+                    YamlBigInteger("99999999999999999999".to_string()),
+                    YamlBigInteger("-9223372036854775809".to_string()),
+                ])), ctor.construct(doc.root().unwrap()))
             },
             _ => panic!("document parse failure")
         }
     }
 
     #[test]
-    fn test_single_quoted_parser() {
-        let data = r#"'here''s to "quotes"'"#;
+    fn test_integer_without_digits() {
+        let data = "[0b_, 0x_, 0o_, -0x__]";
         let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
 
         match parser.load().next() {
             Some(Ok(doc)) => {
                 let ctor = YamlStandardConstructor::new();
-                assert_eq!(Ok(YamlString(r#"here's to "quotes""#.to_string())), ctor.construct(doc.root().unwrap()))
+                assert_eq!(Ok(YamlSequence(vec![
+                    YamlString("0b_".to_string()), YamlString("0x_".to_string()),
+                    YamlString("0o_".to_string()), YamlString("-0x__".to_string()),
+                ])), ctor.construct(doc.root().unwrap()))
+            },
+            _ => panic!("document parse failure")
+        }
+
+        let parser = YamlByteParser::init("!!int 0b_".as_bytes(), YamlUtf8Encoding);
+        match parser.load().next() {
+            Some(Ok(doc)) => {
+                let ctor = YamlStandardConstructor::new();
+                let err = ctor.construct(doc.root().unwrap()).unwrap_err();
+                assert_eq!(Some("value does not match its tag tag:yaml.org,2002:int".to_string()), err.problem);
             },
             _ => panic!("document parse failure")
         }
     }
 
     #[test]
-    fn test_underlined_integer() {
-        let data = "[1_000, -2_000_000]";
+    fn test_tagged_float_big_integer() {
+        let data = "[!!float 99999999999999999999, !!float 0x10000000000000000]";
         let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
 
         match parser.load().next() {
             Some(Ok(doc)) => {
                 let ctor = YamlStandardConstructor::new();
-                assert_eq!(Ok(YamlSequence(vec![YamlInteger(1000), YamlInteger(-2000000)])), ctor.construct(doc.root().unwrap()))
+                let expected = YamlStandardData::YamlSequence(vec![
+                    YamlStandardData::YamlFloat(1e20),
+                    YamlStandardData::YamlFloat(18446744073709551616.0)
+                ]);
+                assert_eq!(Ok(expected), ctor.construct(doc.root().unwrap()));
             },
             _ => panic!("document parse failure")
         }
     }
 
     #[test]
-    fn test_negative_radix() {
-        let data = "[-0x30, -0700, -0b110]";
+    fn test_tagged_integer_out_of_range() {
+        let data = "!!int 99999999999999999999";
         let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
 
         match parser.load().next() {
             Some(Ok(doc)) => {
                 let ctor = YamlStandardConstructor::new();
-                assert_eq!(Ok(YamlSequence(vec![YamlInteger(-48), YamlInteger(-448), YamlInteger(-6)])), ctor.construct(doc.root().unwrap()))
+                assert!(ctor.construct(doc.root().unwrap()).is_err());
+            },
+            _ => panic!("document parse failure")
+        }
+    }
+
+    fn construct_with_schema(schema: YamlSchema, data: &str) -> YamlStandardData {
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        match parser.load().next() {
+            Some(Ok(doc)) => YamlStandardConstructor::with_schema(schema).construct(doc.root().unwrap()).unwrap(),
+            _ => panic!("document parse failure")
+        }
+    }
+
+    #[test]
+    fn test_yaml11_schema() {
+        let data = "[on, Off, NO, 190:20:30, -1:30, 1_230.5, 20:30.15, '', 0x1f, 2002-12-14]";
+        let value = construct_with_schema(YamlSchema::Yaml11Schema, data);
+        assert_eq!(YamlSequence(vec![YamlBool(true), YamlBool(false), YamlBool(false), YamlInteger(685230), YamlInteger(-90),
+            YamlFloat(1230.5), YamlFloat(1230.15), YamlString("".to_string()), YamlInteger(31),
+            YamlTimestamp(YamlTimestamp { year: 2002, month: 12, day: 14, time: None })]), value);
+
+        assert_eq!(YamlMapping(vec![(YamlString("a".to_string()), YamlNull)]), construct_with_schema(YamlSchema::Yaml11Schema, "a:"));
+    }
+
+    #[test]
+    fn test_default_schema() {
+        let data = "[on, off, 1:20, yes, NO, 017, 0x1f, 1_000, 09, 2002-12-14]";
+        let expected = YamlSequence(vec![YamlString("on".to_string()), YamlString("off".to_string()),
+            YamlString("1:20".to_string()), YamlBool(true), YamlBool(false), YamlInteger(15), YamlInteger(31),
+            YamlInteger(1000), YamlFloat(9.0), YamlTimestamp(YamlTimestamp { year: 2002, month: 12, day: 14, time: None })]);
+        assert_eq!(expected, construct_with_schema(YamlSchema::YamlDefaultSchema, data));
+
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let doc = parser.load().next().unwrap().unwrap();
+        assert_eq!(Ok(expected), YamlStandardConstructor::new().construct(doc.root().unwrap()));
+    }
+
+    #[test]
+    fn test_yaml11_malformed_numbers() {
+        let data = "[._, -._, 0_, 09, 1e5, 99999999999999999999999:30, 1:30.5]";
+        let value = construct_with_schema(YamlSchema::Yaml11Schema, data);
+        assert_eq!(YamlSequence(vec![YamlString("._".to_string()), YamlString("-._".to_string()),
+            YamlString("0_".to_string()), YamlString("09".to_string()), YamlFloat(100000.0),
+            YamlBigInteger("99999999999999999999999:30".to_string()), YamlFloat(90.5)]), value);
+    }
+
+    #[test]
+    fn test_empty_plain_scalar() {
+        // YAML 1.1, the 1.2 core schema and the default schema resolve an empty
+        // plain scalar to null, while an empty quoted scalar stays a string
+        let data = "- \n- ''\n- a:\n";
+        let expected = YamlSequence(vec![YamlNull, YamlString("".to_string()),
+            YamlMapping(vec![(YamlString("a".to_string()), YamlNull)])]);
+        assert_eq!(expected, construct_with_schema(YamlSchema::Yaml11Schema, data));
+        assert_eq!(expected, construct_with_schema(YamlSchema::YamlCoreSchema, data));
+        assert_eq!(expected, construct_with_schema(YamlSchema::YamlDefaultSchema, data));
+
+        assert_eq!(YamlSequence(vec![YamlString("".to_string()), YamlString("".to_string()),
+            YamlMapping(vec![(YamlString("a".to_string()), YamlString("".to_string()))])]),
+            construct_with_schema(YamlSchema::YamlJsonSchema, data));
+    }
+
+    #[test]
+    fn test_core_schema() {
+        let data = "[NO, yes, True, 0o17, 017, 0x1f, 1_000, +12, 1e3, -.INF, ~, 2002-12-14, 190:20:30]";
+        let value = construct_with_schema(YamlSchema::YamlCoreSchema, data);
+        assert_eq!(YamlSequence(vec![YamlString("NO".to_string()), YamlString("yes".to_string()), YamlBool(true),
+            YamlInteger(15), YamlInteger(17), YamlInteger(31), YamlString("1_000".to_string()), YamlInteger(12),
+            YamlFloat(1000.0), YamlFloat(f64::NEG_INFINITY), YamlNull, YamlString("2002-12-14".to_string()),
+            YamlString("190:20:30".to_string())]), value);
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let data = "[null, true, True, -12, +12, 0.5, 1e3, ~, .inf]";
+        let value = construct_with_schema(YamlSchema::YamlJsonSchema, data);
+        assert_eq!(YamlSequence(vec![YamlNull, YamlBool(true), YamlString("True".to_string()), YamlInteger(-12),
+            YamlString("+12".to_string()), YamlFloat(0.5), YamlFloat(1000.0), YamlString("~".to_string()),
+            YamlString(".inf".to_string())]), value);
+    }
+
+    #[test]
+    fn test_failsafe_schema() {
+        let data = "[null, true, 12, !!int '12']";
+        let value = construct_with_schema(YamlSchema::YamlFailsafeSchema, data);
+        assert_eq!(YamlSequence(vec![YamlString("null".to_string()), YamlString("true".to_string()),
+            YamlString("12".to_string()), YamlInteger(12)]), value);
+    }
+
+    #[test]
+    fn test_unknown_tags() {
+        let data = "[!Ref foo, !env '5', !env 5, !Sub [a, b], !<tag:example.com,2000:point> {x: 1}]";
+        let value = construct_with_schema(YamlSchema::Yaml11Schema, data);
+        let tagged = |tag: &str, data| YamlTagged(tag.to_string(), Box::new(data));
+        assert_eq!(YamlSequence(vec![
+            tagged("!Ref", YamlString("foo".to_string())),
+            tagged("!env", YamlString("5".to_string())),
+            tagged("!env", YamlInteger(5)),
+            tagged("!Sub", YamlSequence(vec![YamlString("a".to_string()), YamlString("b".to_string())])),
+            tagged("tag:example.com,2000:point", YamlMapping(vec![(YamlString("x".to_string()), YamlInteger(1))]))
+        ]), value);
+    }
+
+    #[test]
+    fn test_tag_handlers_send() {
+        let mut ctor = YamlStandardConstructor::new();
+        ctor.register_tag("!env", |_, _| Ok(YamlNull));
+
+        let value = thread::spawn(move || {
+            let parser = YamlByteParser::init("!env HOME".as_bytes(), YamlUtf8Encoding);
+            let doc = parser.load().next().unwrap().unwrap();
+            ctor.construct(doc.root().unwrap())
+        }).join().unwrap();
+        assert_eq!(Ok(YamlNull), value);
+    }
+
+    #[test]
+    fn test_tag_handlers() {
+        let data = "%TAG !e! tag:example.com,2000:\n---\n[!env HOME, !e!upper [a, b], !env {}]";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let mut ctor = YamlStandardConstructor::new();
+        ctor.register_tag("!env", |_, node| match node {
+            YamlNode::YamlScalarNode(scalar) => Ok(YamlString(format!("${{{}}}", scalar.get_value()))),
+            _ => Err(YamlError::new(YamlErrorType::YAML_PARSER_ERROR, Some("!env expects a scalar".to_string())))
+        });
+        ctor.register_tag("tag:example.com,2000:upper", |ctor, node| match node {
+            YamlNode::YamlSequenceNode(seq) => {
+                let items: Result<Vec<YamlStandardData>, YamlError> = seq.values().map(|item| {
+                    ctor.construct(item).map(|data| match data {
+                        YamlString(s) => YamlString(s.to_uppercase()),
+                        other => other
+                    })
+                }).collect();
+                items.map(YamlSequence)
             },
+            _ => panic!("unexpected node")
+        });
+
+        let doc = parser.load().next().unwrap().unwrap();
+        let seq = match doc.root() {
+            Some(YamlNode::YamlSequenceNode(seq)) => seq,
             _ => panic!("document parse failure")
+        };
+        let mut values = seq.values();
+        assert_eq!(YamlString("${HOME}".to_string()), ctor.construct(values.next().unwrap()).unwrap());
+        assert_eq!(YamlSequence(vec![YamlString("A".to_string()), YamlString("B".to_string())]),
+            ctor.construct(values.next().unwrap()).unwrap());
+        assert!(ctor.construct(values.next().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_spanned_constructor() {
+        let data = "a: 1\nb:\n  - 'x'\n  - !!set {c}\n";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let doc = parser.load().next().unwrap().unwrap();
+        let spanned = YamlSpannedConstructor::new().construct(doc.root().unwrap()).unwrap();
+
+        let mark = |index, line, column| YamlMark { index: index, line: line, column: column };
+        assert_eq!(mark(0, 0, 0), spanned.start);
+        let pairs = match spanned.data {
+            YamlSpannedData::YamlMapping(ref pairs) => pairs,
+            _ => panic!("expected a mapping")
+        };
+
+        let (ref key, ref value) = pairs[0];
+        assert_eq!(YamlSpannedData::YamlValue(YamlString("a".to_string())), key.data);
+        assert_eq!((mark(3, 0, 3), mark(4, 0, 4)), (value.start, value.end));
+        assert_eq!(Some(YamlScalarStyle::YamlPlainScalarStyle), value.style);
+
+        let items = match pairs[1].1.data {
+            YamlSpannedData::YamlSequence(ref items) => items,
+            _ => panic!("expected a sequence")
+        };
+        assert_eq!((mark(12, 2, 4), mark(15, 2, 7)), (items[0].start, items[0].end));
+        assert_eq!(Some(YamlScalarStyle::YamlSingleQuotedScalarStyle), items[0].style);
+        assert_eq!(None, items[0].tag);
+        assert_eq!(YamlSpanned {
+            data: YamlSpannedData::YamlValue(YamlSet(vec![YamlString("c".to_string())])),
+            start: mark(20, 3, 4),
+            end: mark(29, 3, 13),
+            tag: Some("tag:yaml.org,2002:set".to_string()),
+            style: None
+        }, items[1]);
+
+        let standard = YamlStandardConstructor::new().construct(doc.root().unwrap()).unwrap();
+        assert_eq!(standard, spanned.into_standard());
+    }
+
+    #[test]
+    fn test_spanned_merge_keys() {
+        let data = "- &base {x: 1}\n- <<: *base\n  y: 2\n";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let doc = parser.load().next().unwrap().unwrap();
+        let spanned = YamlSpannedConstructor::new().construct(doc.root().unwrap()).unwrap();
+
+        let merged = match spanned.data {
+            YamlSpannedData::YamlSequence(ref items) => items[1].clone(),
+            _ => panic!("expected a sequence")
+        };
+        match merged.data {
+            YamlSpannedData::YamlMapping(ref pairs) => {
+                assert_eq!(0, pairs[0].1.start.line);
+                assert_eq!(2, pairs[1].1.start.line);
+            },
+            _ => panic!("expected a mapping")
+        }
+        assert_eq!(YamlMapping(vec![(YamlString("x".to_string()), YamlInteger(1)), (YamlString("y".to_string()), YamlInteger(2))]),
+            YamlStandardData::from(merged));
+    }
+
+    #[test]
+    fn test_spanned_large_mapping() {
+        let keys: Vec<String> = (0..5000).map(|i| format!("[k{}]: {}", i, i)).collect();
+        let data = format!("- &a {{{}}}\n- {{<<: *a, [k1]: x}}\n- {{{}, [k0]: x}}\n", keys.join(", "), keys.join(", "));
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let doc = parser.load().next().unwrap().unwrap();
+
+        let ctor = YamlSpannedConstructor::new();
+        match ctor.construct(doc.root().unwrap()).unwrap().data {
+            YamlSpannedData::YamlSequence(ref items) => match items[1].data {
+                YamlSpannedData::YamlMapping(ref pairs) => assert_eq!(5000, pairs.len()),
+                _ => panic!("expected a mapping")
+            },
+            _ => panic!("expected a sequence")
+        }
+
+        let options = YamlConstructorOptions { unique_keys: true, ..Default::default() };
+        let ctor = YamlSpannedConstructor::with_constructor(YamlStandardConstructor::with_options(&options));
+        let err = ctor.construct(doc.root().unwrap()).unwrap_err();
+        let context = err.context.unwrap();
+        assert_eq!((2, 3), (context.context_mark.line, context.context_mark.column));
+    }
+
+    #[test]
+    fn test_unique_keys() {
+        let data = "a: 1\n1: x\nb: 2\n0x1: y\n";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let doc = parser.load().next().unwrap().unwrap();
+
+        let ctor = YamlStandardConstructor::new();
+        assert!(ctor.construct(doc.root().unwrap()).is_ok());
+
+        let options = YamlConstructorOptions { unique_keys: true, ..Default::default() };
+        let ctor = YamlStandardConstructor::with_options(&options);
+        let err = ctor.construct(doc.root().unwrap()).unwrap_err();
+        assert_eq!(Some("second occurrence".to_string()), err.problem);
+        let context = err.context.unwrap();
+        assert_eq!(YamlMark { index: 15, line: 3, column: 0 }, context.problem_mark);
+        assert_eq!(Some("found duplicate key; first occurrence".to_string()), context.context);
+        assert_eq!(YamlMark { index: 5, line: 1, column: 0 }, context.context_mark);
+
+        let err = YamlSpannedConstructor::with_constructor(ctor.clone()).construct(doc.root().unwrap()).unwrap_err();
+        assert_eq!(5, err.context.unwrap().context_mark.index);
+    }
+
+    #[test]
+    fn test_unique_keys_hashed() {
+        let cases = [
+            ("{-0.0: a, 0.0: b}", true),
+            ("{[1, {a: b}]: x, [0x1, {a: b}]: y}", true),
+            ("[&k [1], {*k : x, [1]: y}]", true),
+            ("{.nan: a, .nan: b}", false),
+            ("{1: a, 1.0: b, '1': c}", false),
+        ];
+        for &(data, duplicate) in cases.iter() {
+            let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+            let doc = parser.load().next().unwrap().unwrap();
+
+            let mut ctor = YamlStandardConstructor::new();
+            ctor.set_unique_keys(true);
+            let res = ctor.construct(doc.root().unwrap());
+            assert_eq!(duplicate, res.is_err(), "{}", data);
+        }
+
+        let mut ctor = YamlStandardConstructor::new();
+        ctor.set_unique_keys(true);
+        let keys: Vec<String> = (0..5000).map(|i| format!("k{}: {}", i, i)).collect();
+        let data = format!("{{{}, k0: x}}", keys.join(", "));
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let doc = parser.load().next().unwrap().unwrap();
+        let err = ctor.construct(doc.root().unwrap()).unwrap_err();
+        assert_eq!(1, err.context.unwrap().context_mark.index);
+    }
+
+    #[test]
+    fn test_unique_keys_with_merge() {
+        let data = "- &base {x: 1}\n- <<: *base\n  x: 2\n";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let doc = parser.load().next().unwrap().unwrap();
+
+        let mut ctor = YamlStandardConstructor::new();
+        ctor.set_unique_keys(true);
+        assert!(ctor.construct(doc.root().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_recursive_alias() {
+        for data in ["&a [*a]", "&a {x: [1, *a]}", "- &a {<<: *a}"].iter() {
+            let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+            let doc = parser.load().next().unwrap().unwrap();
+
+            let err = YamlStandardConstructor::new().construct(doc.root().unwrap()).unwrap_err();
+            assert_eq!(Some("found a recursive alias".to_string()), err.problem);
+            let err = YamlSpannedConstructor::new().construct(doc.root().unwrap()).unwrap_err();
+            assert_eq!(Some("found a recursive alias".to_string()), err.problem);
+        }
+    }
+
+    #[test]
+    fn test_shared_nodes_constructed_once() {
+        let data = "- &a !count [1]\n- *a\n- *a\n- !count [1]\n";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let doc = parser.load().next().unwrap().unwrap();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut ctor = YamlStandardConstructor::new();
+        let counter = calls.clone();
+        ctor.register_tag("!count", move |_, _| {
+            Ok(YamlInteger(counter.fetch_add(1, Ordering::SeqCst) as i64 + 1))
+        });
+
+        assert_eq!(Ok(YamlSequence(vec![YamlInteger(1), YamlInteger(1), YamlInteger(1), YamlInteger(2)])),
+            ctor.construct(doc.root().unwrap()));
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+
+        // the cache doesn't outlive a single construction
+        assert_eq!(Ok(YamlSequence(vec![YamlInteger(3), YamlInteger(3), YamlInteger(3), YamlInteger(4)])),
+            ctor.construct(doc.root().unwrap()));
+    }
+
+    #[test]
+    fn test_construction_limits() {
+        let data = "- &a [1, [2]]\n- [*a, *a]\n";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let doc = parser.load().next().unwrap().unwrap();
+
+        let cases = [
+            (YamlLimits { max_nodes: Some(14), ..Default::default() }, None),
+            (YamlLimits { max_nodes: Some(13), ..Default::default() }, Some("exceeded the maximum number of nodes")),
+            (YamlLimits { max_alias_dereferences: Some(1), ..Default::default() }, Some("exceeded the maximum number of alias dereferences")),
+            (YamlLimits { max_depth: Some(3), ..Default::default() }, None),
+            (YamlLimits { max_depth: Some(2), ..Default::default() }, Some("exceeded the maximum nesting depth")),
+        ];
+        for &(limits, problem) in cases.iter() {
+            let mut ctor = YamlStandardConstructor::new();
+            ctor.set_limits(limits);
+            match ctor.construct(doc.root().unwrap()) {
+                Ok(_) => assert_eq!(None, problem),
+                Err(err) => {
+                    assert!(err.limit_exceeded);
+                    assert_eq!(problem.map(|p| p.to_string()), err.problem);
+                }
+            }
+            // the counts start over with every construction
+            assert_eq!(problem.is_none(), ctor.construct(doc.root().unwrap()).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_alias_expansion_bounded_by_max_nodes() {
+        // each level refers to the previous one ten times, for 10^9 strings
+        let mut data = "- &l0 [x, x, x, x, x, x, x, x, x, x]\n".to_string();
+        for level in 1..9 {
+            let alias = format!("*l{}", level - 1);
+            data.push_str(&format!("- &l{} [{}]\n", level, vec![&alias[..]; 10].join(", ")));
+        }
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let doc = parser.load().next().unwrap().unwrap();
+
+        let mut ctor = YamlStandardConstructor::new();
+        ctor.set_limits(YamlLimits { max_nodes: Some(100000), ..Default::default() });
+        let err = ctor.construct(doc.root().unwrap()).unwrap_err();
+        assert_eq!(Some("exceeded the maximum number of nodes".to_string()), err.problem);
+    }
+
+    #[test]
+    fn test_aliases_share_values() {
+        // without limits, the expansion is never copied
+        let mut data = "- &l0 [x, x, x, x, x, x, x, x, x, x]\n".to_string();
+        for level in 1..9 {
+            let alias = format!("*l{}", level - 1);
+            data.push_str(&format!("- &l{} [{}]\n", level, vec![&alias[..]; 10].join(", ")));
+        }
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let doc = parser.load().next().unwrap().unwrap();
+
+        let shared = |data: &YamlStandardData| match *data {
+            YamlShared(ref value) => value.clone(),
+            ref other => panic!("expected a shared value, found {:?}", other)
+        };
+        let levels = match YamlStandardConstructor::new().construct(doc.root().unwrap()) {
+            Ok(YamlSequence(levels)) => levels,
+            res => panic!("unexpected result: {:?}", res)
+        };
+        let last = match *levels[8].resolved() {
+            YamlSequence(ref items) => items.clone(),
+            _ => panic!("expected a sequence")
+        };
+        for item in last.iter() {
+            assert!(Arc::ptr_eq(&shared(&levels[7]), &shared(item)));
+        }
+
+        let spanned = YamlSpannedConstructor::new().construct(doc.root().unwrap()).unwrap();
+        let levels = match spanned.data {
+            YamlSpannedData::YamlSequence(ref levels) => levels,
+            _ => panic!("expected a sequence")
+        };
+        match *levels[1].data.resolved() {
+            YamlSpannedData::YamlSequence(ref items) => {
+                // references carry the position of the aliased node
+                assert_eq!(levels[0].start, items[0].start);
+                assert_eq!(levels[0].data, items[9].data);
+            },
+            _ => panic!("expected a sequence")
+        }
+        match spanned.to_standard() {
+            YamlSequence(ref levels) => match *levels[8].resolved() {
+                YamlSequence(ref items) => assert!(Arc::ptr_eq(&shared(&items[0]), &shared(&items[9]))),
+                _ => panic!("expected a sequence")
+            },
+            _ => panic!("expected a sequence")
         }
     }
 }
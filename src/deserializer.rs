@@ -0,0 +1,698 @@
+use serde::de;
+use serde::de::{Deserialize, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+
+use constructor::{YamlStandardData, YamlStandardConstructor};
+use error::{YamlError, YamlErrorContext, YamlMark};
+use event::{YamlEvent, YamlEventSpec, YamlScalarParam};
+use ffi;
+use ffi::{YamlErrorType, YamlScalarStyle};
+use parser::{YamlParser, YamlEventStream, YamlByteParser, YamlIoParser, YamlLimits};
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io::Read;
+use std::str;
+
+static STR_TAG: &str = "tag:yaml.org,2002:str";
+static NULL_TAG: &str = "tag:yaml.org,2002:null";
+
+impl de::Error for YamlError {
+    fn custom<T: fmt::Display>(msg: T) -> YamlError {
+        YamlError::new(YamlErrorType::YAML_PARSER_ERROR, Some(msg.to_string()))
+    }
+}
+
+fn error_at(message: String, mark: &YamlMark) -> YamlError {
+    let context = YamlErrorContext {
+        byte_offset: mark.index,
+        problem_mark: *mark,
+        context: None,
+        context_mark: *mark,
+    };
+
+    YamlError {
+        kind: YamlErrorType::YAML_PARSER_ERROR,
+        problem: Some(message),
+        io_error: None,
+        context: Some(context),
+        limit_exceeded: false
+    }
+}
+
+fn limit_error(message: &str, mark: &YamlMark) -> YamlError {
+    let mut err = error_at(message.to_string(), mark);
+    err.limit_exceeded = true;
+    err
+}
+
+fn exceeds(limit: Option<usize>, value: usize) -> bool {
+    limit.map_or(false, |max| value > max)
+}
+
+struct YamlAnchorRecorder {
+    name: String,
+    depth: usize,
+    events: Vec<YamlEvent>
+}
+
+pub struct YamlDeserializer<'de, P> {
+    events: YamlEventStream<P>,
+    peeked: Option<(YamlEvent, bool)>,
+    replay: VecDeque<YamlEvent>,
+    anchors: HashMap<String, Vec<YamlEvent>>,
+    recorders: Vec<YamlAnchorRecorder>,
+    input: Option<&'de str>,
+    cursor: (usize, usize),
+    mark: YamlMark,
+    stream_started: bool,
+    ctor: YamlStandardConstructor,
+    limits: YamlLimits,
+    nodes: usize,
+    dereferences: usize,
+    depth: usize,
+    document_start: usize
+}
+
+impl<'de, P: YamlParser> YamlDeserializer<'de, P> {
+    pub fn new(events: YamlEventStream<P>) -> YamlDeserializer<'de, P> {
+        YamlDeserializer::with_limits(events, Default::default())
+    }
+
+    // Every alias replays the events of its anchor, so the nodes reached
+    // through an alias count against max_nodes again each time.
+    pub fn with_limits(events: YamlEventStream<P>, limits: YamlLimits) -> YamlDeserializer<'de, P> {
+        YamlDeserializer {
+            events: events,
+            peeked: None,
+            replay: VecDeque::new(),
+            anchors: HashMap::new(),
+            recorders: Vec::new(),
+            input: None,
+            cursor: (0, 0),
+            mark: YamlMark { index: 0, line: 0, column: 0 },
+            stream_started: false,
+            ctor: YamlStandardConstructor::new(),
+            limits: limits,
+            nodes: 0,
+            dereferences: 0,
+            depth: 0,
+            document_start: 0
+        }
+    }
+
+    fn fetch(&mut self) -> Result<(YamlEvent, bool), YamlError> {
+        loop {
+            let (event, replayed) = match self.replay.pop_front() {
+                Some(event) => (event, true),
+                None => match self.events.next() {
+                    Some(Ok(event)) => (event, false),
+                    Some(Err(e)) => return Err(e),
+                    None => return Err(error_at("unexpected end of stream".to_string(), &self.mark))
+                }
+            };
+
+            match event.spec {
+                YamlEventSpec::YamlAliasEvent(ref anchor) => match self.anchors.get(anchor) {
+                    Some(events) => {
+                        self.dereferences += 1;
+                        if exceeds(self.limits.max_alias_dereferences, self.dereferences) {
+                            return Err(limit_error("exceeded the maximum number of alias dereferences", &event.start));
+                        }
+                        for evt in events.iter().rev() {
+                            self.replay.push_front(evt.clone());
+                        }
+                    },
+                    None => return Err(error_at(format!("unknown anchor {}", anchor), &event.start))
+                },
+                _ => return Ok((event, replayed))
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Result<&YamlEvent, YamlError> {
+        if self.peeked.is_none() {
+            let fetched = try!(self.fetch());
+            self.peeked = Some(fetched);
+        }
+
+        match self.peeked {
+            Some((ref event, _)) => Ok(event),
+            None => unreachable!()
+        }
+    }
+
+    fn next(&mut self) -> Result<(YamlEvent, bool), YamlError> {
+        let (event, replayed) = match self.peeked.take() {
+            Some(peeked) => peeked,
+            None => try!(self.fetch())
+        };
+
+        self.mark = event.start;
+        try!(self.check_limits(&event));
+        if !replayed {
+            try!(self.check_document_size(&event));
+        }
+        self.record(&event, replayed);
+
+        Ok((event, replayed))
+    }
+
+    fn check_limits(&mut self, event: &YamlEvent) -> Result<(), YamlError> {
+        match event.spec {
+            YamlEventSpec::YamlScalarEvent(ref param) => {
+                self.nodes += 1;
+                if exceeds(self.limits.max_scalar_length, param.value.len()) {
+                    return Err(limit_error("exceeded the maximum scalar length", &event.start));
+                }
+            },
+            YamlEventSpec::YamlSequenceStartEvent(_) | YamlEventSpec::YamlMappingStartEvent(_) => {
+                self.nodes += 1;
+                self.depth += 1;
+                if exceeds(self.limits.max_depth, self.depth) {
+                    return Err(limit_error("exceeded the maximum nesting depth", &event.start));
+                }
+            },
+            YamlEventSpec::YamlSequenceEndEvent | YamlEventSpec::YamlMappingEndEvent =>
+                self.depth -= 1,
+            _ => return Ok(())
+        }
+
+        if exceeds(self.limits.max_nodes, self.nodes) {
+            return Err(limit_error("exceeded the maximum number of nodes", &event.start));
+        }
+        Ok(())
+    }
+
+    fn check_document_size(&mut self, event: &YamlEvent) -> Result<(), YamlError> {
+        if self.limits.max_document_bytes.is_none() {
+            return Ok(());
+        }
+
+        let end = self.input_offset(event.end.index);
+        if exceeds(self.limits.max_document_bytes, end.saturating_sub(self.document_start)) {
+            return Err(limit_error("exceeded the maximum document size", &event.start));
+        }
+        Ok(())
+    }
+
+    // Replayed events are recorded into the anchors around them, but the
+    // anchors inside them were defined where they were first read, so they are
+    // not defined again and don't replace any later definitions.
+    fn record(&mut self, event: &YamlEvent, replayed: bool) {
+        let mut finished = Vec::new();
+        for (i, recorder) in self.recorders.iter_mut().enumerate() {
+            recorder.events.push(event.clone());
+            match event.spec {
+                YamlEventSpec::YamlSequenceStartEvent(_) | YamlEventSpec::YamlMappingStartEvent(_) =>
+                    recorder.depth += 1,
+                YamlEventSpec::YamlSequenceEndEvent | YamlEventSpec::YamlMappingEndEvent =>
+                    recorder.depth -= 1,
+                _ => ()
+            }
+            if recorder.depth == 0 {
+                finished.push(i);
+            }
+        }
+
+        for i in finished.into_iter().rev() {
+            let recorder = self.recorders.remove(i);
+            self.anchors.insert(recorder.name, recorder.events);
+        }
+
+        if replayed {
+            return;
+        }
+
+        match event.spec {
+            YamlEventSpec::YamlScalarEvent(YamlScalarParam { anchor: Some(ref anchor), .. }) => {
+                self.anchors.insert(anchor.clone(), vec![event.clone()]);
+            },
+            YamlEventSpec::YamlSequenceStartEvent(ref param) | YamlEventSpec::YamlMappingStartEvent(ref param) => {
+                match param.anchor {
+                    Some(ref anchor) => self.recorders.push(YamlAnchorRecorder {
+                        name: anchor.clone(),
+                        depth: 1,
+                        events: vec![event.clone()]
+                    }),
+                    None => ()
+                }
+            },
+            _ => ()
+        }
+    }
+
+    fn unexpected(&self, event: &YamlEvent, expected: &str) -> YamlError {
+        error_at(format!("expected {}, found {:?}", expected, event.spec), &event.start)
+    }
+
+    fn expect<F>(&mut self, expected: &str, is_expected: F) -> Result<(), YamlError>
+        where F: Fn(&YamlEventSpec) -> bool
+    {
+        let (event, _) = try!(self.next());
+        if is_expected(&event.spec) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&event, expected))
+        }
+    }
+
+    fn skip_node(&mut self) -> Result<(), YamlError> {
+        let mut depth = 0;
+        loop {
+            let (event, _) = try!(self.next());
+            match event.spec {
+                YamlEventSpec::YamlSequenceStartEvent(_) | YamlEventSpec::YamlMappingStartEvent(_) => depth += 1,
+                YamlEventSpec::YamlSequenceEndEvent | YamlEventSpec::YamlMappingEndEvent => depth -= 1,
+                YamlEventSpec::YamlScalarEvent(_) => (),
+                _ => return Err(self.unexpected(&event, "a node"))
+            }
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    fn byte_offset(&mut self, char_index: usize) -> Option<usize> {
+        let input = match self.input {
+            Some(input) => input,
+            None => return None
+        };
+
+        if char_index < self.cursor.0 {
+            self.cursor = if input.starts_with('\u{feff}') { (0, 3) } else { (0, 0) };
+        }
+
+        let (mut chars, mut bytes) = self.cursor;
+        let mut it = input[bytes..].chars();
+        while chars < char_index {
+            match it.next() {
+                Some(c) => {
+                    chars += 1;
+                    bytes += c.len_utf8();
+                },
+                None => return None
+            }
+        }
+
+        self.cursor = (chars, bytes);
+        Some(bytes)
+    }
+
+    // the byte offset of a mark, or its character index where the input isn't
+    // at hand, as with a reader
+    fn input_offset(&mut self, char_index: usize) -> usize {
+        match self.byte_offset(char_index) {
+            Some(offset) => offset,
+            None => char_index
+        }
+    }
+
+    // returns the scalar value as a slice of the input, if it appears there verbatim
+    fn borrow_scalar(&mut self, event: &YamlEvent, param: &YamlScalarParam) -> Option<&'de str> {
+        let start = match self.byte_offset(event.start.index) {
+            Some(offset) => offset,
+            None => return None
+        };
+        let end = match self.byte_offset(event.end.index) {
+            Some(offset) => offset,
+            None => return None
+        };
+        let source = match self.input {
+            Some(input) => &input[start..end],
+            None => return None
+        };
+
+        let value = match param.style {
+            YamlScalarStyle::YamlPlainScalarStyle => source,
+            YamlScalarStyle::YamlSingleQuotedScalarStyle | YamlScalarStyle::YamlDoubleQuotedScalarStyle =>
+                if source.len() >= 2 { &source[1..source.len() - 1] } else { return None },
+            _ => return None
+        };
+
+        if value == &param.value[..] {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    fn visit_string<V: Visitor<'de>>(&mut self, event: &YamlEvent, replayed: bool, param: YamlScalarParam, visitor: V) -> Result<V::Value, YamlError> {
+        // strings keep the text of the scalar, but it still has to match its tag
+        if param.tag.is_some() {
+            try!(self.resolve_scalar(event, &param));
+        }
+
+        let borrowed = if replayed { None } else { self.borrow_scalar(event, &param) };
+        match borrowed {
+            Some(s) => visitor.visit_borrowed_str(s),
+            None => visitor.visit_string(param.value)
+        }
+    }
+
+    // a scalar tagged as null which doesn't hold a null value is an error,
+    // as it is for the constructor
+    fn peek_null(&mut self) -> Result<bool, YamlError> {
+        let event = try!(self.peek()).clone();
+        let param = match event.spec {
+            YamlEventSpec::YamlScalarEvent(ref param) => param,
+            _ => return Ok(false)
+        };
+        match param.tag {
+            Some(ref tag) if &tag[..] == NULL_TAG => self.resolve_scalar(&event, param).map(|_| true),
+            Some(_) => Ok(false),
+            None => Ok(param.style == YamlScalarStyle::YamlPlainScalarStyle
+                && (param.value.is_empty() || self.ctor.resolve_plain_scalar(param.value.clone()) == YamlStandardData::YamlNull))
+        }
+    }
+
+    fn resolve_scalar(&self, event: &YamlEvent, param: &YamlScalarParam) -> Result<YamlStandardData, YamlError> {
+        match param.tag {
+            None if param.style == YamlScalarStyle::YamlPlainScalarStyle =>
+                Ok(self.ctor.resolve_plain_scalar(param.value.clone())),
+            None => Ok(YamlStandardData::YamlString(param.value.clone())),
+            Some(ref tag) if &tag[..] == STR_TAG || !tag.starts_with("tag:yaml.org,2002:") =>
+                Ok(YamlStandardData::YamlString(param.value.clone())),
+            Some(ref tag) => self.ctor.resolve_explicit_scalar(tag.clone(), param.value.clone(),
+                param.style == YamlScalarStyle::YamlPlainScalarStyle, &event.start)
+        }
+    }
+
+    fn visit_scalar<V: Visitor<'de>>(&mut self, event: YamlEvent, replayed: bool, param: YamlScalarParam, visitor: V) -> Result<V::Value, YamlError> {
+        match try!(self.resolve_scalar(&event, &param)) {
+            YamlStandardData::YamlInteger(i) => visitor.visit_i64(i),
+            YamlStandardData::YamlUnsignedInteger(u) => visitor.visit_u64(u),
+            YamlStandardData::YamlFloat(f) => visitor.visit_f64(f),
+            YamlStandardData::YamlBool(b) => visitor.visit_bool(b),
+            YamlStandardData::YamlNull => visitor.visit_unit(),
+            YamlStandardData::YamlBinary(bytes) => visitor.visit_byte_buf(bytes),
+            _ => self.visit_string(&event, replayed, param, visitor)
+        }
+    }
+
+    fn start_document(&mut self) -> Result<bool, YamlError> {
+        if !self.stream_started {
+            let (event, _) = try!(self.next());
+            match event.spec {
+                YamlEventSpec::YamlStreamStartEvent(_) => self.stream_started = true,
+                _ => return Err(self.unexpected(&event, "start of stream"))
+            }
+        }
+
+        match try!(self.peek()).spec {
+            YamlEventSpec::YamlStreamEndEvent => return Ok(false),
+            _ => ()
+        }
+
+        let (event, _) = try!(self.next());
+        match event.spec {
+            YamlEventSpec::YamlDocumentStartEvent(..) => {
+                // anchors and limits do not carry over from one document to the next
+                self.anchors.clear();
+                self.nodes = 0;
+                self.dereferences = 0;
+                self.depth = 0;
+                if self.limits.max_document_bytes.is_some() {
+                    self.document_start = self.input_offset(event.start.index);
+                }
+                Ok(true)
+            },
+            _ => Err(self.unexpected(&event, "start of document"))
+        }
+    }
+
+    fn attach_mark(&self, mut err: YamlError) -> YamlError {
+        if err.context.is_none() {
+            err.context = Some(YamlErrorContext {
+                byte_offset: self.mark.index,
+                problem_mark: self.mark,
+                context: None,
+                context_mark: self.mark
+            });
+        }
+        err
+    }
+
+    pub fn next_document<T: Deserialize<'de>>(&mut self) -> Option<Result<T, YamlError>> {
+        let res = match self.start_document() {
+            Ok(true) => T::deserialize(&mut *self).and_then(|value| {
+                self.expect("end of document", |spec| match *spec {
+                    YamlEventSpec::YamlDocumentEndEvent(_) => true,
+                    _ => false
+                }).map(|_| value)
+            }),
+            Ok(false) => return None,
+            Err(e) => Err(e)
+        };
+
+        Some(res.map_err(|e| self.attach_mark(e)))
+    }
+
+    fn single_document<T: Deserialize<'de>>(&mut self) -> Result<T, YamlError> {
+        let value = match self.next_document() {
+            Some(res) => try!(res),
+            None => try!(T::deserialize(().into_deserializer()).map_err(|e| self.attach_mark(e)))
+        };
+
+        let (event, _) = try!(self.next());
+        match event.spec {
+            YamlEventSpec::YamlStreamEndEvent => Ok(value),
+            _ => Err(error_at("expected a single document in the stream".to_string(), &event.start))
+        }
+    }
+}
+
+pub fn from_str<'de, T: Deserialize<'de>>(s: &'de str) -> Result<T, YamlError> {
+    from_str_with_limits(s, Default::default())
+}
+
+pub fn from_str_with_limits<'de, T: Deserialize<'de>>(s: &'de str, limits: YamlLimits) -> Result<T, YamlError> {
+    let parser = YamlByteParser::init(s.as_bytes(), ffi::YamlEncoding::YamlUtf8Encoding);
+    let mut de = YamlDeserializer::with_limits(parser.parse(), limits);
+    de.input = Some(s);
+    de.cursor = if s.starts_with('\u{feff}') { (0, 3) } else { (0, 0) };
+    de.single_document()
+}
+
+pub fn from_slice<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> Result<T, YamlError> {
+    from_slice_with_limits(bytes, Default::default())
+}
+
+pub fn from_slice_with_limits<'de, T: Deserialize<'de>>(bytes: &'de [u8], limits: YamlLimits) -> Result<T, YamlError> {
+    match str::from_utf8(bytes) {
+        Ok(s) => from_str_with_limits(s, limits),
+        Err(_) => {
+            let parser = YamlByteParser::init(bytes, ffi::YamlEncoding::YamlAnyEncoding);
+            YamlDeserializer::with_limits(parser.parse(), limits).single_document()
+        }
+    }
+}
+
+pub fn from_reader<T: DeserializeOwned>(reader: &mut Read) -> Result<T, YamlError> {
+    from_reader_with_limits(reader, Default::default())
+}
+
+pub fn from_reader_with_limits<T: DeserializeOwned>(reader: &mut Read, limits: YamlLimits) -> Result<T, YamlError> {
+    let parser = YamlIoParser::init(reader, ffi::YamlEncoding::YamlAnyEncoding);
+    YamlDeserializer::with_limits(parser.parse(), limits).single_document()
+}
+
+impl<'de, 'a, P: YamlParser> de::Deserializer<'de> for &'a mut YamlDeserializer<'de, P> {
+    type Error = YamlError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, YamlError> {
+        let (event, replayed) = try!(self.next());
+        match event.spec.clone() {
+            YamlEventSpec::YamlScalarEvent(param) => self.visit_scalar(event, replayed, param, visitor),
+            YamlEventSpec::YamlSequenceStartEvent(_) => {
+                let value = try!(visitor.visit_seq(YamlSeqAccess { de: &mut *self }));
+                try!(self.expect("end of sequence", |spec| *spec == YamlEventSpec::YamlSequenceEndEvent));
+                Ok(value)
+            },
+            YamlEventSpec::YamlMappingStartEvent(_) => {
+                let value = try!(visitor.visit_map(YamlMapAccess { de: &mut *self }));
+                try!(self.expect("end of mapping", |spec| *spec == YamlEventSpec::YamlMappingEndEvent));
+                Ok(value)
+            },
+            _ => Err(self.unexpected(&event, "a node"))
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, YamlError> {
+        match try!(self.peek()).spec {
+            YamlEventSpec::YamlScalarEvent(_) => (),
+            _ => return self.deserialize_any(visitor)
+        }
+
+        let (event, replayed) = try!(self.next());
+        match event.spec.clone() {
+            YamlEventSpec::YamlScalarEvent(param) => self.visit_string(&event, replayed, param, visitor),
+            _ => unreachable!()
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, YamlError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, YamlError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, YamlError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, YamlError> {
+        let is_null = try!(self.peek_null());
+
+        if is_null {
+            try!(self.next());
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, YamlError> {
+        let is_null = try!(self.peek_null());
+
+        if is_null {
+            try!(self.next());
+            visitor.visit_unit()
+        } else {
+            self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, YamlError> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, YamlError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, YamlError> {
+        let local_tag = {
+            let event = try!(self.peek());
+            let tag = match event.spec {
+                YamlEventSpec::YamlScalarEvent(ref param) => &param.tag,
+                YamlEventSpec::YamlSequenceStartEvent(ref param) | YamlEventSpec::YamlMappingStartEvent(ref param) => &param.tag,
+                _ => &None
+            };
+            match *tag {
+                Some(ref tag) if tag.starts_with('!') && tag.len() > 1 => Some(tag[1..].to_string()),
+                _ => None
+            }
+        };
+
+        match local_tag {
+            Some(variant) => {
+                // the tag names the variant, and the tagged node is its content
+                match self.peeked {
+                    Some((ref mut event, _)) => match event.spec {
+                        YamlEventSpec::YamlScalarEvent(ref mut param) => param.tag = None,
+                        YamlEventSpec::YamlSequenceStartEvent(ref mut param) | YamlEventSpec::YamlMappingStartEvent(ref mut param) => param.tag = None,
+                        _ => ()
+                    },
+                    None => ()
+                }
+                return visitor.visit_enum(YamlEnumAccess { de: self, tagged: Some(variant) });
+            },
+            None => ()
+        }
+
+        let (event, _) = try!(self.next());
+        match event.spec {
+            YamlEventSpec::YamlScalarEvent(param) => visitor.visit_enum(param.value.into_deserializer()),
+            YamlEventSpec::YamlMappingStartEvent(_) => {
+                let value = try!(visitor.visit_enum(YamlEnumAccess { de: &mut *self, tagged: None }));
+                try!(self.expect("end of mapping", |spec| *spec == YamlEventSpec::YamlMappingEndEvent));
+                Ok(value)
+            },
+            _ => Err(self.unexpected(&event, "an enum variant"))
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, YamlError> {
+        try!(self.skip_node());
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 bytes byte_buf
+        seq tuple tuple_struct map struct
+    }
+}
+
+struct YamlSeqAccess<'a, 'de: 'a, P: 'a> {
+    de: &'a mut YamlDeserializer<'de, P>
+}
+
+impl<'de, 'a, P: YamlParser> de::SeqAccess<'de> for YamlSeqAccess<'a, 'de, P> {
+    type Error = YamlError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, YamlError> {
+        match try!(self.de.peek()).spec {
+            YamlEventSpec::YamlSequenceEndEvent => Ok(None),
+            _ => seed.deserialize(&mut *self.de).map(Some)
+        }
+    }
+}
+
+struct YamlMapAccess<'a, 'de: 'a, P: 'a> {
+    de: &'a mut YamlDeserializer<'de, P>
+}
+
+impl<'de, 'a, P: YamlParser> de::MapAccess<'de> for YamlMapAccess<'a, 'de, P> {
+    type Error = YamlError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, YamlError> {
+        match try!(self.de.peek()).spec {
+            YamlEventSpec::YamlMappingEndEvent => Ok(None),
+            _ => seed.deserialize(&mut *self.de).map(Some)
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, YamlError> {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+struct YamlEnumAccess<'a, 'de: 'a, P: 'a> {
+    de: &'a mut YamlDeserializer<'de, P>,
+    tagged: Option<String>
+}
+
+impl<'de, 'a, P: YamlParser> de::EnumAccess<'de> for YamlEnumAccess<'a, 'de, P> {
+    type Error = YamlError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), YamlError> {
+        let variant = match self.tagged {
+            Some(ref variant) => try!(seed.deserialize(variant.clone().into_deserializer())),
+            None => try!(seed.deserialize(&mut *self.de))
+        };
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a, P: YamlParser> de::VariantAccess<'de> for YamlEnumAccess<'a, 'de, P> {
+    type Error = YamlError;
+
+    fn unit_variant(self) -> Result<(), YamlError> {
+        de::Deserialize::deserialize(&mut *self.de)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, YamlError> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, YamlError> {
+        de::Deserializer::deserialize_seq(&mut *self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, YamlError> {
+        de::Deserializer::deserialize_map(&mut *self.de, visitor)
+    }
+}
@@ -2,29 +2,399 @@ use libc;
 
 use codecs;
 use ffi;
+use ffi::{YamlEncoding, YamlErrorType, YamlScalarStyle, YamlSequenceStyle};
 use ffi::yaml_node_type_t::*;
-use error::YamlMark;
+use error::{YamlError, YamlMark};
+use event::{YamlEvent, YamlEventSpec, YamlVersionDirective, YamlTagDirective};
+use parser::YamlLimits;
 
 use std::ptr;
 use std::mem;
+use std::ffi::CString;
+use std::collections::HashMap;
 
 pub struct YamlDocument {
-    document_mem: ffi::yaml_document_t
+    document_mem: ffi::yaml_document_t,
+    explicit_tags: Vec<bool>,
+    aliased: Vec<bool>
 }
 
-impl YamlDocument {
-    pub unsafe fn parser_load(parser: &mut ffi::yaml_parser_t) -> Option<Box<YamlDocument>> {
-        let mut document = Box::new(YamlDocument {
-            document_mem: mem::uninitialized()
-        });
+// The document owns all of the memory behind its pointers, which libyaml
+// allocated for it alone, so it can be moved to another thread.
+unsafe impl Send for YamlDocument {}
 
-        if ffi::yaml_parser_load(parser, &mut document.document_mem) == 0 {
-            None
+fn to_c_mark(mark: &YamlMark) -> ffi::yaml_mark_t {
+    ffi::yaml_mark_t {
+        index: mark.index as libc::size_t,
+        line: mark.line as libc::size_t,
+        column: mark.column as libc::size_t
+    }
+}
+
+unsafe fn parse_event(parser: &mut ffi::yaml_parser_t) -> Option<YamlEvent> {
+    let mut event: ffi::yaml_event_t = mem::zeroed();
+
+    if ffi::yaml_parser_parse(parser, &mut event) == 0 {
+        None
+    } else {
+        let res = YamlEvent::load(&event);
+        event.delete();
+        Some(res)
+    }
+}
+
+// Composes documents from parser events the same way yaml_parser_load() does,
+// but keeps track of which nodes were explicitly tagged in the source, and
+// allows anchors to be redefined.
+struct YamlComposer<'a> {
+    parser: &'a mut ffi::yaml_parser_t,
+    anchors: HashMap<String, libc::c_int>,
+    limits: &'a YamlLimits,
+    aliases: usize,
+    limit_exceeded: bool
+}
+
+fn exceeds(limit: Option<usize>, value: usize) -> bool {
+    limit.map_or(false, |max| value > max)
+}
+
+impl<'a> YamlComposer<'a> {
+    unsafe fn set_error(&mut self, problem: &'static [u8], problem_mark: &YamlMark,
+            context: Option<(&'static [u8], YamlMark)>) {
+        self.parser.error = YamlErrorType::YAML_COMPOSER_ERROR;
+        self.parser.problem = problem.as_ptr() as *const libc::c_char;
+        self.parser.problem_mark = to_c_mark(problem_mark);
+        match context {
+            Some((context, context_mark)) => {
+                self.parser.context = context.as_ptr() as *const libc::c_char;
+                self.parser.context_mark = to_c_mark(&context_mark);
+            },
+            None => ()
+        }
+    }
+
+    // libyaml has no error type for an exceeded limit, so the parser is stopped
+    // with a composer error and the limit is reported alongside
+    unsafe fn set_limit_error(&mut self, problem: &'static [u8], problem_mark: &YamlMark) {
+        self.set_error(problem, problem_mark, None);
+        self.limit_exceeded = true;
+        self.parser.problem_offset = self.input_offset() as libc::size_t;
+    }
+
+    // The reader decodes the input ahead of the scanner, so the characters
+    // still waiting in the buffer are taken off the decoded byte count.
+    unsafe fn input_offset(&self) -> usize {
+        let unread = match self.parser.encoding {
+            YamlEncoding::YamlUtf8Encoding =>
+                self.parser.buffer.last as usize - self.parser.buffer.pointer as usize,
+            _ => self.parser.unread as usize * 2
+        };
+        (self.parser.offset as usize).saturating_sub(unread)
+    }
+
+    // an anchor which is defined again refers to its latest node from then on,
+    // as the spec says; yaml_parser_load() rejects it instead
+    fn register_anchor(&mut self, anchor: &Option<String>, index: libc::c_int) {
+        match *anchor {
+            Some(ref name) => {
+                self.anchors.insert(name.clone(), index);
+            },
+            None => ()
+        }
+    }
+
+    unsafe fn add_node(&mut self, doc: &mut YamlDocument, spec: &YamlEventSpec, start: &YamlMark, end: &YamlMark) -> libc::c_int {
+        let (tag, default_tag) = match *spec {
+            YamlEventSpec::YamlScalarEvent(ref param) => (&param.tag, &b"tag:yaml.org,2002:str\0"[..]),
+            YamlEventSpec::YamlSequenceStartEvent(ref param) => (&param.tag, &b"tag:yaml.org,2002:seq\0"[..]),
+            YamlEventSpec::YamlMappingStartEvent(ref param) => (&param.tag, &b"tag:yaml.org,2002:map\0"[..]),
+            _ => panic!("unexpected event")
+        };
+
+        // the non-specific tag "!" is replaced by the default tag, but still
+        // counts as explicit since it forbids resolving plain scalars
+        let c_tag = match *tag {
+            Some(ref t) if &t[..] != "!" => CString::new(t.as_bytes()).unwrap(),
+            _ => CString::new(&default_tag[..default_tag.len() - 1]).unwrap()
+        };
+        let tag_ptr = c_tag.as_ptr() as *const ffi::yaml_char_t;
+
+        let index = match *spec {
+            YamlEventSpec::YamlScalarEvent(ref param) => ffi::yaml_document_add_scalar(&mut doc.document_mem,
+                tag_ptr, param.value.as_ptr(), param.value.len() as libc::c_int, param.style),
+            YamlEventSpec::YamlSequenceStartEvent(ref param) => ffi::yaml_document_add_sequence(&mut doc.document_mem,
+                tag_ptr, param.style),
+            YamlEventSpec::YamlMappingStartEvent(ref param) => ffi::yaml_document_add_mapping(&mut doc.document_mem,
+                tag_ptr, param.style),
+            _ => panic!("unexpected event")
+        };
+        if index == 0 {
+            self.parser.error = YamlErrorType::YAML_MEMORY_ERROR;
+            return 0;
+        }
+
+        let node = ffi::yaml_document_get_node(&doc.document_mem, index) as *mut ffi::yaml_node_t;
+        (*node).start_mark = to_c_mark(start);
+        (*node).end_mark = to_c_mark(end);
+        doc.explicit_tags.push(tag.is_some());
+        doc.aliased.push(false);
+
+        if exceeds(self.limits.max_nodes, doc.explicit_tags.len()) {
+            self.set_limit_error(b"exceeded the maximum number of nodes\0", start);
+            return 0;
+        }
+
+        index
+    }
+
+    unsafe fn append(&mut self, doc: &mut YamlDocument, stack: &mut Vec<(libc::c_int, bool, Option<libc::c_int>)>, index: libc::c_int) -> bool {
+        let parent = match stack.last_mut() {
+            None => return true,
+            Some(parent) => parent
+        };
+
+        let ok = if !parent.1 {
+            ffi::yaml_document_append_sequence_item(&mut doc.document_mem, parent.0, index) != 0
         } else {
-            Some(document)
+            match parent.2.take() {
+                None => {
+                    parent.2 = Some(index);
+                    true
+                },
+                Some(key) => ffi::yaml_document_append_mapping_pair(&mut doc.document_mem, parent.0, key, index) != 0
+            }
+        };
+        if !ok {
+            self.parser.error = YamlErrorType::YAML_MEMORY_ERROR;
+        }
+        ok
+    }
+
+    unsafe fn load_document(&mut self, version_directive: Option<YamlVersionDirective>,
+            tag_directives: &[YamlTagDirective], start_implicit: bool, start_mark: &YamlMark) -> Option<Box<YamlDocument>> {
+        let mut doc = match YamlDocument::initialize(version_directive, tag_directives, start_implicit, true) {
+            Ok(doc) => doc,
+            Err(_) => {
+                self.parser.error = YamlErrorType::YAML_MEMORY_ERROR;
+                return None;
+            }
+        };
+        doc.document_mem.start_mark = to_c_mark(start_mark);
+        let start_offset = self.input_offset();
+
+        // open collections, whether they are mappings, and the pending mapping key
+        let mut stack: Vec<(libc::c_int, bool, Option<libc::c_int>)> = Vec::new();
+
+        loop {
+            let event = match parse_event(self.parser) {
+                Some(event) => event,
+                None => return None
+            };
+
+            if exceeds(self.limits.max_document_bytes, self.input_offset().saturating_sub(start_offset)) {
+                self.set_limit_error(b"exceeded the maximum document size\0", &event.start);
+                return None;
+            }
+
+            match event.spec {
+                YamlEventSpec::YamlDocumentEndEvent(implicit) => {
+                    doc.document_mem.end_implicit = if implicit { 1 } else { 0 };
+                    doc.document_mem.end_mark = to_c_mark(&event.end);
+                    return Some(doc);
+                },
+                YamlEventSpec::YamlAliasEvent(ref anchor) => {
+                    let index = match self.anchors.get(anchor) {
+                        Some(&index) => index,
+                        None => {
+                            self.set_error(b"found undefined alias\0", &event.start, None);
+                            return None;
+                        }
+                    };
+                    doc.aliased[index as usize - 1] = true;
+                    self.aliases += 1;
+                    if exceeds(self.limits.max_alias_dereferences, self.aliases) {
+                        self.set_limit_error(b"exceeded the maximum number of alias dereferences\0", &event.start);
+                        return None;
+                    }
+                    if !self.append(&mut doc, &mut stack, index) {
+                        return None;
+                    }
+                },
+                YamlEventSpec::YamlScalarEvent(ref param) => {
+                    if exceeds(self.limits.max_scalar_length, param.value.len()) {
+                        self.set_limit_error(b"exceeded the maximum scalar length\0", &event.start);
+                        return None;
+                    }
+                    let index = self.add_node(&mut doc, &event.spec, &event.start, &event.end);
+                    if index == 0 {
+                        return None;
+                    }
+                    self.register_anchor(&param.anchor, index);
+                    if !self.append(&mut doc, &mut stack, index) {
+                        return None;
+                    }
+                },
+                YamlEventSpec::YamlSequenceStartEvent(ref param) | YamlEventSpec::YamlMappingStartEvent(ref param) => {
+                    if exceeds(self.limits.max_depth, stack.len() + 1) {
+                        self.set_limit_error(b"exceeded the maximum nesting depth\0", &event.start);
+                        return None;
+                    }
+                    let index = self.add_node(&mut doc, &event.spec, &event.start, &event.end);
+                    if index == 0 {
+                        return None;
+                    }
+                    self.register_anchor(&param.anchor, index);
+                    if !self.append(&mut doc, &mut stack, index) {
+                        return None;
+                    }
+                    let is_mapping = match event.spec {
+                        YamlEventSpec::YamlMappingStartEvent(..) => true,
+                        _ => false
+                    };
+                    stack.push((index, is_mapping, None));
+                },
+                YamlEventSpec::YamlSequenceEndEvent | YamlEventSpec::YamlMappingEndEvent => {
+                    let (index, _, _) = stack.pop().unwrap();
+                    let node = ffi::yaml_document_get_node(&doc.document_mem, index) as *mut ffi::yaml_node_t;
+                    (*node).end_mark = to_c_mark(&event.end);
+                },
+                // the parser has stopped after an earlier error
+                _ => return None
+            }
+        }
+    }
+}
+
+impl YamlDocument {
+    unsafe fn empty() -> Box<YamlDocument> {
+        Box::new(YamlDocument {
+            document_mem: mem::zeroed(),
+            explicit_tags: Vec::new(),
+            aliased: Vec::new()
+        })
+    }
+
+    fn initialize(version_directive: Option<YamlVersionDirective>,
+            tag_directives: &[YamlTagDirective],
+            start_implicit: bool,
+            end_implicit: bool) -> Result<Box<YamlDocument>, YamlError>
+    {
+        let mut vsn_dir = ffi::yaml_version_directive_t { major: 0, minor: 0 };
+        let c_vsn_dir = match version_directive {
+            Some(directive) => {
+                vsn_dir.major = directive.major as libc::c_int;
+                vsn_dir.minor = directive.minor as libc::c_int;
+                &vsn_dir as *const ffi::yaml_version_directive_t
+            },
+            None => ptr::null()
+        };
+
+        let mut c_strs = Vec::new();
+        for tag in tag_directives.iter() {
+            match (CString::new(tag.handle.as_bytes()), CString::new(tag.prefix.as_bytes())) {
+                (Ok(handle), Ok(prefix)) => c_strs.push((handle, prefix)),
+                _ => return Err(composer_error("Nul bytes in tag directives"))
+            }
+        }
+        let c_tag_dirs: Vec<ffi::yaml_tag_directive_t> = c_strs.iter().map(|(handle, prefix)|
+            ffi::yaml_tag_directive_t {
+                handle: handle.as_ptr(),
+                prefix: prefix.as_ptr()
+            }
+        ).collect();
+
+        unsafe {
+            let tag_dir_start = c_tag_dirs.as_ptr();
+            let tag_dir_end = tag_dir_start.offset(c_tag_dirs.len() as isize);
+            let c_start_implicit = if start_implicit { 1 } else { 0 };
+            let c_end_implicit = if end_implicit { 1 } else { 0 };
+
+            let mut doc = YamlDocument::empty();
+
+            if ffi::yaml_document_initialize(&mut doc.document_mem, c_vsn_dir,
+                    tag_dir_start, tag_dir_end, c_start_implicit, c_end_implicit) == 0 {
+                return Err(YamlError::new(YamlErrorType::YAML_MEMORY_ERROR, None));
+            }
+
+            Ok(doc)
         }
     }
 
+    // On failure the error is left in the parser, and Err(true) tells that a
+    // YamlLimits bound was exceeded
+    pub unsafe fn parser_load(parser: &mut ffi::yaml_parser_t, limits: &YamlLimits) -> Result<Box<YamlDocument>, bool> {
+        if parser.stream_end_produced != 0 {
+            return Ok(YamlDocument::empty());
+        }
+
+        if parser.stream_start_produced == 0 {
+            match parse_event(parser) {
+                Some(ref event) if event.spec != YamlEventSpec::YamlNoEvent => (),
+                _ => return Err(false)
+            }
+        }
+
+        let event = match parse_event(parser) {
+            Some(event) => event,
+            None => return Err(false)
+        };
+
+        match event.spec {
+            YamlEventSpec::YamlStreamEndEvent => Ok(YamlDocument::empty()),
+            YamlEventSpec::YamlDocumentStartEvent(vsn_dir, ref tag_dirs, implicit) => {
+                let mut composer = YamlComposer {
+                    parser: parser,
+                    anchors: HashMap::new(),
+                    limits: limits,
+                    aliases: 0,
+                    limit_exceeded: false
+                };
+                match composer.load_document(vsn_dir, &tag_dirs[..], implicit, &event.start) {
+                    Some(doc) => Ok(doc),
+                    None => Err(composer.limit_exceeded)
+                }
+            },
+            _ => Err(false)
+        }
+    }
+
+    fn node_index(&self, node: &ffi::yaml_node_t) -> usize {
+        let offset = node as *const ffi::yaml_node_t as usize - self.document_mem.nodes.start as usize;
+        offset / mem::size_of::<ffi::yaml_node_t>() + 1
+    }
+
+    fn is_tag_explicit(&self, node: &ffi::yaml_node_t) -> bool {
+        match self.explicit_tags.get(self.node_index(node) - 1) {
+            Some(&explicit) => explicit,
+            None => false
+        }
+    }
+
+    fn is_aliased(&self, node: &ffi::yaml_node_t) -> bool {
+        match self.aliased.get(self.node_index(node) - 1) {
+            Some(&aliased) => aliased,
+            None => false
+        }
+    }
+
+    pub unsafe fn emitter_dump(mut self: Box<YamlDocument>, emitter: &mut ffi::yaml_emitter_t) -> bool {
+        let res = ffi::yaml_emitter_dump(emitter, &mut self.document_mem) != 0;
+
+        // yaml_emitter_dump() destroys the document whether or not it succeeds,
+        // and may leave dangling pointers behind
+        self.document_mem = mem::zeroed();
+
+        res
+    }
+
+    pub fn start_mark(&self) -> YamlMark {
+        YamlMark::conv(&self.document_mem.start_mark)
+    }
+
+    pub fn end_mark(&self) -> YamlMark {
+        YamlMark::conv(&self.document_mem.end_mark)
+    }
+
     pub fn is_empty(&self) -> bool {
         unsafe {
             ffi::yaml_document_get_root_node(&self.document_mem) == ptr::null()
@@ -40,6 +410,7 @@ impl YamlDocument {
             YAML_SCALAR_NODE => {
                 let scalar_data: &ffi::yaml_scalar_node_t = mem::transmute(&node.data);
                 YamlNode::YamlScalarNode(YamlScalarData {
+                    doc: self,
                     node: node,
                     data: scalar_data
                 })
@@ -89,14 +460,257 @@ impl Drop for YamlDocument {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct YamlNodeHandle(usize);
+
+enum YamlBuilderNode {
+    Scalar(Option<CString>, String, YamlScalarStyle),
+    Sequence(Option<CString>, YamlSequenceStyle, Vec<YamlNodeHandle>),
+    Mapping(Option<CString>, YamlSequenceStyle, Vec<(YamlNodeHandle, YamlNodeHandle)>),
+}
+
+pub struct YamlDocumentBuilder {
+    version_directive: Option<YamlVersionDirective>,
+    tag_directives: Vec<YamlTagDirective>,
+    start_implicit: bool,
+    end_implicit: bool,
+    nodes: Vec<YamlBuilderNode>,
+    root: Option<YamlNodeHandle>
+}
+
+fn composer_error(message: &str) -> YamlError {
+    YamlError::new(YamlErrorType::YAML_COMPOSER_ERROR, Some(message.to_string()))
+}
+
+fn to_c_tag(tag: Option<&str>) -> Result<Option<CString>, YamlError> {
+    match tag {
+        None => Ok(None),
+        Some(s) => match CString::new(s.as_bytes()) {
+            Ok(cstr) => Ok(Some(cstr)),
+            Err(_) => Err(composer_error("Nul bytes in tag"))
+        }
+    }
+}
+
+fn c_tag_ptr(tag: &Option<CString>) -> *const ffi::yaml_char_t {
+    match *tag {
+        Some(ref s) => s.as_ptr() as *const ffi::yaml_char_t,
+        None => ptr::null()
+    }
+}
+
+impl YamlDocumentBuilder {
+    pub fn new(version_directive: Option<YamlVersionDirective>,
+            tag_directives: &[YamlTagDirective],
+            start_implicit: bool,
+            end_implicit: bool) -> YamlDocumentBuilder
+    {
+        YamlDocumentBuilder {
+            version_directive: version_directive,
+            tag_directives: tag_directives.to_vec(),
+            start_implicit: start_implicit,
+            end_implicit: end_implicit,
+            nodes: Vec::new(),
+            root: None
+        }
+    }
+
+    fn push(&mut self, node: YamlBuilderNode) -> YamlNodeHandle {
+        self.nodes.push(node);
+        YamlNodeHandle(self.nodes.len() - 1)
+    }
+
+    pub fn add_scalar(&mut self, tag: Option<&str>, value: &str, style: YamlScalarStyle) -> Result<YamlNodeHandle, YamlError> {
+        let c_tag = try!(to_c_tag(tag));
+        Ok(self.push(YamlBuilderNode::Scalar(c_tag, value.to_string(), style)))
+    }
+
+    pub fn add_sequence(&mut self, tag: Option<&str>, style: YamlSequenceStyle) -> Result<YamlNodeHandle, YamlError> {
+        let c_tag = try!(to_c_tag(tag));
+        Ok(self.push(YamlBuilderNode::Sequence(c_tag, style, Vec::new())))
+    }
+
+    pub fn add_mapping(&mut self, tag: Option<&str>, style: YamlSequenceStyle) -> Result<YamlNodeHandle, YamlError> {
+        let c_tag = try!(to_c_tag(tag));
+        Ok(self.push(YamlBuilderNode::Mapping(c_tag, style, Vec::new())))
+    }
+
+    fn check_handle(&self, handle: YamlNodeHandle) -> Result<(), YamlError> {
+        if handle.0 < self.nodes.len() {
+            Ok(())
+        } else {
+            Err(composer_error("Invalid node handle"))
+        }
+    }
+
+    pub fn append_sequence_item(&mut self, sequence: YamlNodeHandle, item: YamlNodeHandle) -> Result<(), YamlError> {
+        try!(self.check_handle(sequence));
+        try!(self.check_handle(item));
+        match self.nodes[sequence.0] {
+            YamlBuilderNode::Sequence(_, _, ref mut items) => {
+                items.push(item);
+                Ok(())
+            },
+            _ => Err(composer_error("Node is not a sequence"))
+        }
+    }
+
+    pub fn append_mapping_pair(&mut self, mapping: YamlNodeHandle, key: YamlNodeHandle, value: YamlNodeHandle) -> Result<(), YamlError> {
+        try!(self.check_handle(mapping));
+        try!(self.check_handle(key));
+        try!(self.check_handle(value));
+        match self.nodes[mapping.0] {
+            YamlBuilderNode::Mapping(_, _, ref mut pairs) => {
+                pairs.push((key, value));
+                Ok(())
+            },
+            _ => Err(composer_error("Node is not a mapping"))
+        }
+    }
+
+    pub fn set_root(&mut self, root: YamlNodeHandle) -> Result<(), YamlError> {
+        try!(self.check_handle(root));
+        self.root = Some(root);
+        Ok(())
+    }
+
+    // libyaml always treats the first node of a document as its root, so nodes are
+    // added in depth-first order starting from the root. Nodes which are not
+    // reachable from the root are left out.
+    fn add_node(&self, doc: &mut YamlDocument, handle: YamlNodeHandle, indices: &mut Vec<libc::c_int>) -> Result<(), YamlError> {
+        if indices[handle.0] != 0 {
+            // a node reached twice is shared, like an aliased one
+            doc.aliased[indices[handle.0] as usize - 1] = true;
+            return Ok(());
+        }
+
+        let index = unsafe {
+            match self.nodes[handle.0] {
+                YamlBuilderNode::Scalar(ref tag, ref value, style) =>
+                    ffi::yaml_document_add_scalar(&mut doc.document_mem, c_tag_ptr(tag),
+                        value.as_ptr(), value.len() as libc::c_int, style),
+                YamlBuilderNode::Sequence(ref tag, style, _) =>
+                    ffi::yaml_document_add_sequence(&mut doc.document_mem, c_tag_ptr(tag), style),
+                YamlBuilderNode::Mapping(ref tag, style, _) =>
+                    ffi::yaml_document_add_mapping(&mut doc.document_mem, c_tag_ptr(tag), style),
+            }
+        };
+        if index == 0 {
+            return Err(YamlError::new(YamlErrorType::YAML_MEMORY_ERROR, None));
+        }
+        indices[handle.0] = index;
+        let tag = match self.nodes[handle.0] {
+            YamlBuilderNode::Scalar(ref tag, _, _) => tag,
+            YamlBuilderNode::Sequence(ref tag, _, _) => tag,
+            YamlBuilderNode::Mapping(ref tag, _, _) => tag
+        };
+        doc.explicit_tags.push(tag.is_some());
+        doc.aliased.push(false);
+
+        match self.nodes[handle.0] {
+            YamlBuilderNode::Scalar(..) => (),
+            YamlBuilderNode::Sequence(_, _, ref items) => {
+                for &item in items.iter() {
+                    try!(self.add_node(doc, item, indices));
+                }
+            },
+            YamlBuilderNode::Mapping(_, _, ref pairs) => {
+                for &(key, value) in pairs.iter() {
+                    try!(self.add_node(doc, key, indices));
+                    try!(self.add_node(doc, value, indices));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn append_children(&self, doc: &mut YamlDocument, indices: &[libc::c_int]) -> Result<(), YamlError> {
+        for (i, node) in self.nodes.iter().enumerate() {
+            if indices[i] == 0 {
+                continue;
+            }
+
+            let ok = unsafe {
+                match *node {
+                    YamlBuilderNode::Scalar(..) => true,
+                    YamlBuilderNode::Sequence(_, _, ref items) => items.iter().all(|item|
+                        ffi::yaml_document_append_sequence_item(&mut doc.document_mem,
+                            indices[i], indices[item.0]) != 0
+                    ),
+                    YamlBuilderNode::Mapping(_, _, ref pairs) => pairs.iter().all(|&(key, value)|
+                        ffi::yaml_document_append_mapping_pair(&mut doc.document_mem,
+                            indices[i], indices[key.0], indices[value.0]) != 0
+                    ),
+                }
+            };
+            if !ok {
+                return Err(YamlError::new(YamlErrorType::YAML_MEMORY_ERROR, None));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn build(self) -> Result<Box<YamlDocument>, YamlError> {
+        let mut doc = try!(YamlDocument::initialize(self.version_directive, &self.tag_directives[..],
+            self.start_implicit, self.end_implicit));
+
+        match self.root {
+            None => (),
+            Some(root) => {
+                let mut indices = vec![0; self.nodes.len()];
+                try!(self.add_node(&mut doc, root, &mut indices));
+                try!(self.append_children(&mut doc, &indices[..]));
+            }
+        }
+
+        Ok(doc)
+    }
+}
+
 pub enum YamlNode<'r> {
     YamlScalarNode(YamlScalarData<'r>),
     YamlSequenceNode(YamlSequenceData<'r>),
     YamlMappingNode(YamlMappingData<'r>),
 }
 
+impl<'r> YamlNode<'r> {
+    pub fn node_id(&self) -> usize {
+        match *self {
+            YamlNode::YamlScalarNode(ref scalar) => scalar.node_id(),
+            YamlNode::YamlSequenceNode(ref sequence) => sequence.node_id(),
+            YamlNode::YamlMappingNode(ref mapping) => mapping.node_id()
+        }
+    }
+
+    pub fn is_aliased(&self) -> bool {
+        match *self {
+            YamlNode::YamlScalarNode(ref scalar) => scalar.is_aliased(),
+            YamlNode::YamlSequenceNode(ref sequence) => sequence.is_aliased(),
+            YamlNode::YamlMappingNode(ref mapping) => mapping.is_aliased()
+        }
+    }
+}
+
 pub trait YamlNodeData {
     unsafe fn internal_node<'r>(&'r self) -> &'r ffi::yaml_node_t;
+    unsafe fn internal_document<'r>(&'r self) -> &'r YamlDocument;
+
+    // Identifies the node within its document. An alias resolves to the node
+    // of its anchor, so both have the same id.
+    fn node_id(&self) -> usize {
+        unsafe {
+            self.internal_document().node_index(self.internal_node())
+        }
+    }
+
+    // whether the node is referred to by an alias
+    fn is_aliased(&self) -> bool {
+        unsafe {
+            self.internal_document().is_aliased(self.internal_node())
+        }
+    }
 
     fn tag(&self) -> Option<String> {
         unsafe {
@@ -104,6 +718,13 @@ pub trait YamlNodeData {
         }
     }
 
+    // whether the tag was written in the source rather than assigned by default
+    fn is_tag_explicit(&self) -> bool {
+        unsafe {
+            self.internal_document().is_tag_explicit(self.internal_node())
+        }
+    }
+
     fn start_mark(&self) -> YamlMark {
         unsafe {
             YamlMark::conv(&self.internal_node().start_mark)
@@ -118,6 +739,7 @@ pub trait YamlNodeData {
 }
 
 pub struct YamlScalarData<'r> {
+    doc: &'r YamlDocument,
     node: &'r ffi::yaml_node_t,
     data: &'r ffi::yaml_scalar_node_t
 }
@@ -126,6 +748,10 @@ impl<'r> YamlNodeData for YamlScalarData<'r> {
     unsafe fn internal_node<'a>(&'a self) -> &'a ffi::yaml_node_t {
         self.node
     }
+
+    unsafe fn internal_document<'a>(&'a self) -> &'a YamlDocument {
+        self.doc
+    }
 }
 
 impl<'r> YamlScalarData<'r> {
@@ -148,6 +774,10 @@ impl<'r> YamlNodeData for YamlSequenceData<'r> {
     unsafe fn internal_node<'a>(&'a self) -> &'a ffi::yaml_node_t {
         self.node
     }
+
+    unsafe fn internal_document<'a>(&'a self) -> &'a YamlDocument {
+        self.doc
+    }
 }
 
 impl<'r> YamlSequenceData<'r> {
@@ -194,6 +824,10 @@ impl<'r> YamlNodeData for YamlMappingData<'r> {
     unsafe fn internal_node<'a>(&'a self) -> &'a ffi::yaml_node_t {
         self.node
     }
+
+    unsafe fn internal_document<'a>(&'a self) -> &'a YamlDocument {
+        self.doc
+    }
 }
 
 impl<'r> YamlMappingData<'r> {
@@ -231,3 +865,214 @@ impl<'r> Iterator for YamlMappingIter<'r> {
     }
 }
 
+
+#[cfg(test)]
+mod test {
+    use document::{YamlDocument, YamlDocumentBuilder, YamlNode, YamlNodeData};
+    use parser::{YamlParser, YamlByteParser, YamlLimits};
+    use error::YamlError;
+    use constructor::{YamlConstructor, YamlStandardConstructor, YamlStandardData};
+    use ffi::YamlErrorType;
+    use ffi::YamlEncoding::YamlUtf8Encoding;
+    use ffi::YamlScalarStyle::*;
+    use ffi::YamlSequenceStyle::*;
+
+    fn scalar_value(node: YamlNode) -> String {
+        match node {
+            YamlNode::YamlScalarNode(scalar) => scalar.get_value(),
+            _ => panic!("unexpected node")
+        }
+    }
+
+    #[test]
+    fn test_build_document() {
+        let mut builder = YamlDocumentBuilder::new(None, &[], true, true);
+        let a = builder.add_scalar(None, "a", YamlPlainScalarStyle).unwrap();
+        let one = builder.add_scalar(Some("tag:yaml.org,2002:int"), "1", YamlPlainScalarStyle).unwrap();
+        let seq = builder.add_sequence(None, YamlFlowSequenceStyle).unwrap();
+        let map = builder.add_mapping(None, YamlBlockSequenceStyle).unwrap();
+        builder.append_sequence_item(seq, one).unwrap();
+        builder.append_sequence_item(seq, one).unwrap();
+        builder.append_mapping_pair(map, a, seq).unwrap();
+        builder.set_root(map).unwrap();
+
+        let doc = builder.build().unwrap();
+        match doc.root() {
+            Some(YamlNode::YamlMappingNode(mapping)) => {
+                assert_eq!(Some("tag:yaml.org,2002:map".to_string()), mapping.tag());
+                let pairs: Vec<(String, Vec<String>)> = mapping.pairs().map(|(key, value)| {
+                    match value {
+                        YamlNode::YamlSequenceNode(seq) => (scalar_value(key), seq.values().map(scalar_value).collect()),
+                        _ => panic!("unexpected node")
+                    }
+                }).collect();
+                assert_eq!(vec![("a".to_string(), vec!["1".to_string(), "1".to_string()])], pairs);
+            },
+            _ => panic!("unexpected root")
+        }
+    }
+
+    #[test]
+    fn test_build_explicit_tags() {
+        let mut builder = YamlDocumentBuilder::new(None, &[], true, true);
+        let tagged = builder.add_scalar(Some("tag:yaml.org,2002:str"), "123", YamlPlainScalarStyle).unwrap();
+        let plain = builder.add_scalar(None, "123", YamlPlainScalarStyle).unwrap();
+        let seq = builder.add_sequence(None, YamlBlockSequenceStyle).unwrap();
+        builder.append_sequence_item(seq, tagged).unwrap();
+        builder.append_sequence_item(seq, plain).unwrap();
+        builder.append_sequence_item(seq, plain).unwrap();
+        builder.set_root(seq).unwrap();
+
+        let doc = builder.build().unwrap();
+        match doc.root() {
+            Some(YamlNode::YamlSequenceNode(seq)) => {
+                assert!(!seq.is_tag_explicit());
+                let items: Vec<(bool, bool)> = seq.values().map(|node| match node {
+                    YamlNode::YamlScalarNode(scalar) => (scalar.is_tag_explicit(), scalar.is_aliased()),
+                    _ => panic!("unexpected node")
+                }).collect();
+                assert_eq!(vec![(true, false), (false, true), (false, true)], items);
+            },
+            _ => panic!("unexpected root")
+        }
+
+        let ctor = YamlStandardConstructor::new();
+        assert_eq!(YamlStandardData::YamlSequence(vec![
+            YamlStandardData::YamlString("123".to_string()),
+            YamlStandardData::YamlInteger(123),
+            YamlStandardData::YamlInteger(123)
+        ]), ctor.construct(doc.root().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn test_build_empty_document() {
+        let builder = YamlDocumentBuilder::new(None, &[], true, true);
+        let doc = builder.build().unwrap();
+        assert!(doc.is_empty());
+    }
+
+    #[test]
+    fn test_build_invalid_append() {
+        let mut builder = YamlDocumentBuilder::new(None, &[], true, true);
+        let a = builder.add_scalar(None, "a", YamlPlainScalarStyle).unwrap();
+        let b = builder.add_scalar(None, "b", YamlPlainScalarStyle).unwrap();
+
+        match builder.append_sequence_item(a, b) {
+            Err(e) => assert_eq!(YamlErrorType::YAML_COMPOSER_ERROR, e.kind),
+            Ok(_) => panic!("appended an item to a scalar")
+        }
+    }
+
+    #[test]
+    fn test_load_explicit_tags() {
+        let data = "- !!str 1\n- 2\n- ! 3\n";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let doc = parser.load().next().unwrap().unwrap();
+
+        match doc.root() {
+            Some(YamlNode::YamlSequenceNode(seq)) => {
+                assert!(!seq.is_tag_explicit());
+                assert_eq!((3, 0), (seq.end_mark().line, seq.end_mark().column));
+                let items: Vec<(Option<String>, bool, usize)> = seq.values().map(|node| match node {
+                    YamlNode::YamlScalarNode(scalar) => (scalar.tag(), scalar.is_tag_explicit(), scalar.start_mark().line),
+                    _ => panic!("unexpected node")
+                }).collect();
+                assert_eq!(vec![
+                    (Some("tag:yaml.org,2002:str".to_string()), true, 0),
+                    (Some("tag:yaml.org,2002:str".to_string()), false, 1),
+                    (Some("tag:yaml.org,2002:str".to_string()), true, 2),
+                ], items);
+            },
+            _ => panic!("unexpected root")
+        }
+    }
+
+    #[test]
+    fn test_load_undefined_alias() {
+        let data = "a: &x 1\nb: *y\n";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+
+        match parser.load().next() {
+            Some(Err(err)) => {
+                assert_eq!(YamlErrorType::YAML_COMPOSER_ERROR, err.kind);
+                assert_eq!(Some("found undefined alias".to_string()), err.problem);
+                let mark = err.context.unwrap().problem_mark;
+                assert_eq!((1, 3), (mark.line, mark.column));
+            },
+            _ => panic!("unexpected result")
+        }
+    }
+
+    #[test]
+    fn test_load_redefined_anchor() {
+        let data = "- &x a\n- [&y b, &x c]\n- *x\n- *y\n";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let doc = parser.load().next().unwrap().unwrap();
+
+        let items: Vec<YamlNode> = match doc.root() {
+            Some(YamlNode::YamlSequenceNode(seq)) => seq.values().collect(),
+            _ => panic!("expected a sequence")
+        };
+        let aliased: Vec<String> = items.into_iter().skip(2).map(scalar_value).collect();
+        assert_eq!(vec!["c".to_string(), "b".to_string()], aliased);
+    }
+
+    #[test]
+    fn test_node_identity() {
+        let data = "- &x [1]\n- *x\n- [1]\n";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let doc = parser.load().next().unwrap().unwrap();
+
+        let items: Vec<YamlNode> = match doc.root() {
+            Some(YamlNode::YamlSequenceNode(seq)) => seq.values().collect(),
+            _ => panic!("expected a sequence")
+        };
+        assert_eq!(items[0].node_id(), items[1].node_id());
+        assert!(items[0].node_id() != items[2].node_id());
+        assert!(items[0].is_aliased() && items[1].is_aliased());
+        assert!(!items[2].is_aliased());
+    }
+
+    fn load_with_limits(data: &str, limits: YamlLimits) -> Vec<Result<Box<YamlDocument>, YamlError>> {
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        parser.load_with_limits(limits).take(3).collect()
+    }
+
+    fn limit_problem(res: &Result<Box<YamlDocument>, YamlError>) -> Option<String> {
+        match *res {
+            Err(ref err) => {
+                assert!(err.limit_exceeded);
+                err.problem.clone()
+            },
+            Ok(_) => None
+        }
+    }
+
+    #[test]
+    fn test_load_limits() {
+        let data = "a: [[1, 2], &x [3]]\nb: *x\nc: 4567\n";
+        assert!(load_with_limits(data, Default::default())[0].is_ok());
+
+        let cases = [
+            (YamlLimits { max_depth: Some(3), ..Default::default() }, None),
+            (YamlLimits { max_depth: Some(2), ..Default::default() }, Some("exceeded the maximum nesting depth")),
+            (YamlLimits { max_nodes: Some(11), ..Default::default() }, None),
+            (YamlLimits { max_nodes: Some(10), ..Default::default() }, Some("exceeded the maximum number of nodes")),
+            (YamlLimits { max_alias_dereferences: Some(0), ..Default::default() }, Some("exceeded the maximum number of alias dereferences")),
+            (YamlLimits { max_scalar_length: Some(3), ..Default::default() }, Some("exceeded the maximum scalar length")),
+            (YamlLimits { max_document_bytes: Some(20), ..Default::default() }, Some("exceeded the maximum document size")),
+        ];
+        for &(limits, problem) in cases.iter() {
+            let docs = load_with_limits(data, limits);
+            assert_eq!(problem.map(|p| p.to_string()), limit_problem(&docs[0]));
+        }
+    }
+
+    #[test]
+    fn test_load_document_size_limit() {
+        let data = "--- [1, 2]\n--- [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]\n";
+        let docs = load_with_limits(data, YamlLimits { max_document_bytes: Some(20), ..Default::default() });
+        assert!(docs[0].is_ok());
+        assert_eq!(Some("exceeded the maximum document size".to_string()), limit_problem(&docs[1]));
+    }
+}
@@ -1,6 +1,7 @@
 use ffi;
 use error::YamlError;
 use event::{YamlVersionDirective, YamlTagDirective};
+use document::YamlDocument;
 
 use std::str;
 use std::slice;
@@ -18,7 +19,7 @@ pub struct YamlBaseEmitter {
 impl YamlBaseEmitter {
     unsafe fn new() -> YamlBaseEmitter {
         YamlBaseEmitter {
-            emitter_mem: mem::uninitialized()
+            emitter_mem: mem::zeroed()
         }
     }
 }
@@ -31,6 +32,35 @@ impl Drop for YamlBaseEmitter {
     }
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct YamlEmitterOptions {
+    pub canonical: bool,
+    pub indent: usize,
+    pub width: Option<usize>,
+    pub unicode: bool,
+    pub line_break: ffi::yaml_break_t
+}
+
+impl Default for YamlEmitterOptions {
+    fn default() -> YamlEmitterOptions {
+        YamlEmitterOptions {
+            canonical: false,
+            indent: 2,
+            width: Some(80),
+            unicode: false,
+            line_break: ffi::yaml_break_t::YAML_ANY_BREAK
+        }
+    }
+}
+
+fn to_c_int(n: usize) -> libc::c_int {
+    if n > libc::c_int::max_value() as usize {
+        libc::c_int::max_value()
+    } else {
+        n as libc::c_int
+    }
+}
+
 pub struct YamlEmitter<'r> {
     base_emitter: YamlBaseEmitter,
     writer: &'r mut (Write+'r),
@@ -79,6 +109,22 @@ impl<'r> YamlEmitter<'r> {
         }
     }
 
+    pub fn init_with_options<'a>(writer: &'a mut Write, options: &YamlEmitterOptions) -> Box<YamlEmitter<'a>> {
+        let mut emitter = YamlEmitter::init(writer);
+
+        unsafe {
+            let emitter_mem = &mut emitter.base_emitter.emitter_mem;
+            ffi::yaml_emitter_set_canonical(emitter_mem, if options.canonical { 1 } else { 0 });
+            ffi::yaml_emitter_set_indent(emitter_mem, to_c_int(options.indent));
+            // a negative width means unlimited
+            ffi::yaml_emitter_set_width(emitter_mem, options.width.map_or(-1, to_c_int));
+            ffi::yaml_emitter_set_unicode(emitter_mem, if options.unicode { 1 } else { 0 });
+            ffi::yaml_emitter_set_break(emitter_mem, options.line_break);
+        }
+
+        emitter
+    }
+
     fn get_error(&mut self) -> YamlError {
         let emitter_mem = &self.base_emitter.emitter_mem;
         unsafe {
@@ -87,7 +133,8 @@ impl<'r> YamlEmitter<'r> {
                 kind: emitter_mem.error,
                 problem: str::from_utf8(c_problem.to_bytes()).map(|s| s.to_string()).ok(),
                 io_error: None,
-                context: None
+                context: None,
+                limit_exceeded: false
             };
 
             mem::swap(&mut self.io_error, &mut error.io_error);
@@ -105,9 +152,9 @@ impl<'r> YamlEmitter<'r> {
         self.flush()
     }
 
-    fn emit_stream_start_event(&mut self, encoding: ffi::YamlEncoding) -> Result<(), YamlError> {
+    pub fn emit_stream_start_event(&mut self, encoding: ffi::YamlEncoding) -> Result<(), YamlError> {
         unsafe {
-            let mut event = mem::uninitialized();
+            let mut event = mem::zeroed();
 
             if ffi::yaml_stream_start_event_initialize(&mut event, encoding) == 0 {
                 panic!("yaml_stream_start_event_initialize failed!");
@@ -121,9 +168,9 @@ impl<'r> YamlEmitter<'r> {
         }
     }
 
-    fn emit_stream_end_event(&mut self) -> Result<(), YamlError> {
+    pub fn emit_stream_end_event(&mut self) -> Result<(), YamlError> {
         unsafe {
-            let mut event = mem::uninitialized();
+            let mut event = mem::zeroed();
 
             if ffi::yaml_stream_end_event_initialize(&mut event) == 0 {
                 panic!("yaml_stream_end_event_initialize failed!");
@@ -149,7 +196,7 @@ impl<'r> YamlEmitter<'r> {
         self.emit_document_end_event(implicit)
     }
 
-    fn emit_document_start_event(&mut self,
+    pub fn emit_document_start_event(&mut self,
             version_directive: Option<YamlVersionDirective>,
             tag_directives: &[YamlTagDirective],
             implicit: bool)
@@ -175,7 +222,7 @@ impl<'r> YamlEmitter<'r> {
             };
         let tag_dir_start = c_tag_dirs.as_ptr();
         unsafe {
-            let mut event = mem::uninitialized();
+            let mut event = mem::zeroed();
             let tag_dir_end = tag_dir_start.offset(c_tag_dirs.len() as isize);
             let c_implicit = if implicit { 1 } else { 0 };
 
@@ -191,10 +238,10 @@ impl<'r> YamlEmitter<'r> {
         }
     }
 
-    fn emit_document_end_event(&mut self, implicit: bool) -> Result<(), YamlError> {
+    pub fn emit_document_end_event(&mut self, implicit: bool) -> Result<(), YamlError> {
         let c_implicit = if implicit { 1 } else { 0 };
         unsafe {
-            let mut event = mem::uninitialized();
+            let mut event = mem::zeroed();
 
             if ffi::yaml_document_end_event_initialize(&mut event, c_implicit) == 0 {
                 panic!("yaml_stream_end_event_initialize failed!");
@@ -212,7 +259,7 @@ impl<'r> YamlEmitter<'r> {
         let c_anchor = try!(to_c_str(anchor));
 
         unsafe {
-            let mut event = mem::uninitialized();
+            let mut event = mem::zeroed();
 
             let ptr = c_anchor.as_ptr();
             if ffi::yaml_alias_event_initialize(&mut event, ptr as *const ffi::yaml_char_t) != 0 {
@@ -233,19 +280,19 @@ impl<'r> YamlEmitter<'r> {
     {
         let c_anchor = try!(to_c_str_opt(anchor));
         let anchor_ptr = match c_anchor {
-            Some(s) => s.as_ptr(),
+            Some(ref s) => s.as_ptr(),
             None => ptr::null()
         };
         let c_tag = try!(to_c_str_opt(tag));
         let tag_ptr = match c_tag {
-            Some(s) => s.as_ptr(),
+            Some(ref s) => s.as_ptr(),
             None => ptr::null()
         };
         let c_plain_implicit = if plain_implicit { 1 } else { 0 };
         let c_quoted_implicit = if quoted_implicit { 1 } else { 0 };
 
         unsafe {
-            let mut event = mem::uninitialized();
+            let mut event = mem::zeroed();
 
             if ffi::yaml_scalar_event_initialize(&mut event,
                     anchor_ptr as *const ffi::yaml_char_t, tag_ptr as *const ffi::yaml_char_t,
@@ -274,23 +321,23 @@ impl<'r> YamlEmitter<'r> {
         self.emit_sequence_end_event()
     }
 
-    fn emit_sequence_start_event(&mut self, anchor: Option<&str>, tag: Option<&str>, implicit: bool,
+    pub fn emit_sequence_start_event(&mut self, anchor: Option<&str>, tag: Option<&str>, implicit: bool,
         style: ffi::YamlSequenceStyle) -> Result<(), YamlError>
     {
         let c_anchor = try!(to_c_str_opt(anchor));
         let anchor_ptr = match c_anchor {
-            Some(s) => s.as_ptr(),
+            Some(ref s) => s.as_ptr(),
             None => ptr::null()
         };
         let c_tag = try!(to_c_str_opt(tag));
         let tag_ptr = match c_tag {
-            Some(s) => s.as_ptr(),
+            Some(ref s) => s.as_ptr(),
             None => ptr::null()
         };
         let c_implicit = if implicit { 1 } else { 0 };
 
         unsafe {
-            let mut event = mem::uninitialized();
+            let mut event = mem::zeroed();
 
             if ffi::yaml_sequence_start_event_initialize(&mut event,
                     anchor_ptr as *const ffi::yaml_char_t, tag_ptr as *const ffi::yaml_char_t,
@@ -307,9 +354,9 @@ impl<'r> YamlEmitter<'r> {
         }
     }
 
-    fn emit_sequence_end_event(&mut self) -> Result<(), YamlError> {
+    pub fn emit_sequence_end_event(&mut self) -> Result<(), YamlError> {
         unsafe {
-            let mut event = mem::uninitialized();
+            let mut event = mem::zeroed();
 
             if ffi::yaml_sequence_end_event_initialize(&mut event) == 0 {
                 panic!("yaml_sequence_end_event_initialize failed!");
@@ -333,23 +380,23 @@ impl<'r> YamlEmitter<'r> {
         self.emit_mapping_end_event()
     }
 
-    fn emit_mapping_start_event(&mut self, anchor: Option<&str>, tag: Option<&str>, implicit: bool,
+    pub fn emit_mapping_start_event(&mut self, anchor: Option<&str>, tag: Option<&str>, implicit: bool,
         style: ffi::YamlSequenceStyle) -> Result<(), YamlError>
     {
         let c_anchor = try!(to_c_str_opt(anchor));
         let anchor_ptr = match c_anchor {
-            Some(s) => s.as_ptr(),
+            Some(ref s) => s.as_ptr(),
             None => ptr::null()
         };
         let c_tag = try!(to_c_str_opt(tag));
         let tag_ptr = match c_tag {
-            Some(s) => s.as_ptr(),
+            Some(ref s) => s.as_ptr(),
             None => ptr::null()
         };
         let c_implicit = if implicit { 1 } else { 0 };
 
         unsafe {
-            let mut event = mem::uninitialized();
+            let mut event = mem::zeroed();
 
             if ffi::yaml_mapping_start_event_initialize(&mut event,
                     anchor_ptr as *const ffi::yaml_char_t, tag_ptr as *const ffi::yaml_char_t,
@@ -366,9 +413,9 @@ impl<'r> YamlEmitter<'r> {
         }
     }
 
-    fn emit_mapping_end_event(&mut self) -> Result<(), YamlError> {
+    pub fn emit_mapping_end_event(&mut self) -> Result<(), YamlError> {
         unsafe {
-            let mut event = mem::uninitialized();
+            let mut event = mem::zeroed();
 
             if ffi::yaml_mapping_end_event_initialize(&mut event) == 0 {
                 panic!("yaml_mapping_end_event_initialize failed!");
@@ -382,6 +429,51 @@ impl<'r> YamlEmitter<'r> {
         }
     }
 
+    pub fn open(&mut self) -> Result<(), YamlError> {
+        unsafe {
+            if self.base_emitter.emitter_mem.opened != 0 {
+                return Ok(());
+            }
+
+            if ffi::yaml_emitter_open(&mut self.base_emitter.emitter_mem) != 0 {
+                Ok(())
+            } else {
+                Err(self.get_error())
+            }
+        }
+    }
+
+    pub fn close(&mut self) -> Result<(), YamlError> {
+        try!(self.open());
+
+        unsafe {
+            if ffi::yaml_emitter_close(&mut self.base_emitter.emitter_mem) != 0 {
+                Ok(())
+            } else {
+                Err(self.get_error())
+            }
+        }
+    }
+
+    pub fn dump(&mut self, document: Box<YamlDocument>) -> Result<(), YamlError> {
+        // libyaml treats a document without a root node as the end of the
+        // stream, and would close the emitter instead of dumping it
+        if document.is_empty() {
+            return Err(YamlError::new(
+                    ffi::YamlErrorType::YAML_EMITTER_ERROR,
+                    Some("cannot dump an empty document".to_string())
+                ));
+        }
+
+        unsafe {
+            if document.emitter_dump(&mut self.base_emitter.emitter_mem) {
+                Ok(())
+            } else {
+                Err(self.get_error())
+            }
+        }
+    }
+
     pub fn flush(&mut self) -> Result<(), YamlError> {
         unsafe {
             if ffi::yaml_emitter_flush(&mut self.base_emitter.emitter_mem) != 0 {
@@ -409,7 +501,11 @@ extern fn handle_writer_cb(data: *mut YamlEmitter, buffer: *const u8, size: libc
 
 #[cfg(test)]
 mod test {
-    use emitter::YamlEmitter;
+    use emitter::{YamlEmitter, YamlEmitterOptions};
+    use ffi::yaml_break_t::YAML_CRLN_BREAK;
+    use ffi::YamlErrorType::YAML_EMITTER_ERROR;
+    use document::YamlDocumentBuilder;
+    use parser::{YamlParser, YamlByteParser};
     use ffi::YamlEncoding::YamlUtf8Encoding;
     use ffi::YamlScalarStyle::*;
     use ffi::YamlSequenceStyle::*;
@@ -453,4 +549,131 @@ mod test {
         }
         assert_eq!(&writer[..], b"{a: 1, b: 2}\n");
     }
+
+    #[test]
+    fn document_dump_test() {
+        let data = "a: &x [1, 2]\nb: *x\n";
+        let parser = YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let mut writer = Vec::new();
+        {
+            let mut emitter = YamlEmitter::init(&mut writer);
+            for doc in parser.load() {
+                emitter.dump(doc.unwrap()).unwrap();
+            }
+            emitter.close().unwrap();
+        }
+        assert_eq!(&writer[..], &b"a: &id001 [1, 2]\nb: *id001\n"[..]);
+    }
+
+    #[test]
+    fn built_document_dump_test() {
+        let mut builder = YamlDocumentBuilder::new(None, &[], false, true);
+        let seq = builder.add_sequence(None, YamlBlockSequenceStyle).unwrap();
+        let item = builder.add_scalar(None, "item", YamlPlainScalarStyle).unwrap();
+        builder.append_sequence_item(seq, item).unwrap();
+        builder.set_root(seq).unwrap();
+
+        let mut writer = Vec::new();
+        {
+            let mut emitter = YamlEmitter::init(&mut writer);
+            emitter.open().unwrap();
+            emitter.dump(builder.build().unwrap()).unwrap();
+            emitter.close().unwrap();
+        }
+        assert_eq!(&writer[..], &b"---\n- item\n"[..]);
+    }
+
+    #[test]
+    fn empty_document_dump_test() {
+        let builder = YamlDocumentBuilder::new(None, &[], false, true);
+
+        let mut writer = Vec::new();
+        {
+            let mut emitter = YamlEmitter::init(&mut writer);
+            emitter.open().unwrap();
+            let err = emitter.dump(builder.build().unwrap()).unwrap_err();
+            assert_eq!(YAML_EMITTER_ERROR, err.kind);
+
+            // the stream is still open
+            let mut builder = YamlDocumentBuilder::new(None, &[], true, true);
+            let item = builder.add_scalar(None, "item", YamlPlainScalarStyle).unwrap();
+            builder.set_root(item).unwrap();
+            emitter.dump(builder.build().unwrap()).unwrap();
+            emitter.close().unwrap();
+        }
+        assert_eq!(&writer[..], &b"item\n"[..]);
+    }
+
+    #[test]
+    fn empty_stream_close_test() {
+        let mut writer = Vec::new();
+        {
+            let mut emitter = YamlEmitter::init(&mut writer);
+            emitter.close().unwrap();
+            emitter.close().unwrap();
+        }
+        assert_eq!(&writer[..], &b""[..]);
+    }
+
+    #[test]
+    #[allow(unused_must_use)]
+    fn emitter_options_test() {
+        let options = YamlEmitterOptions {
+            indent: 4,
+            width: None,
+            line_break: YAML_CRLN_BREAK,
+            ..Default::default()
+        };
+        let long_value = "a ".repeat(60);
+        let mut writer = Vec::new();
+        {
+            let mut emitter = YamlEmitter::init_with_options(&mut writer, &options);
+            emitter.emit_stream(YamlUtf8Encoding, |e| {
+                e.emit_document(None, &[], true, |e| {
+                    e.emit_mapping(None, None, true, YamlBlockSequenceStyle, |e| {
+                        try!(e.emit_scalar_event(None, None, "a", true, false, YamlPlainScalarStyle));
+                        e.emit_mapping(None, None, true, YamlBlockSequenceStyle, |e| {
+                            try!(e.emit_scalar_event(None, None, "b", true, false, YamlPlainScalarStyle));
+                            e.emit_scalar_event(None, None, long_value.trim(), true, false, YamlPlainScalarStyle)
+                        })
+                    })
+                })
+            });
+        }
+        let expected = format!("a:\r\n    b: {}\r\n", long_value.trim());
+        assert_eq!(expected.as_bytes(), &writer[..]);
+    }
+
+    #[test]
+    #[allow(unused_must_use)]
+    fn emitter_ascii_test() {
+        let options = YamlEmitterOptions { unicode: false, ..Default::default() };
+        let mut writer = Vec::new();
+        {
+            let mut emitter = YamlEmitter::init_with_options(&mut writer, &options);
+            emitter.emit_stream(YamlUtf8Encoding, |e| {
+                e.emit_document(None, &[], true, |e| {
+                    e.emit_scalar_event(None, None, "caf\u{e9}", true, true, YamlAnyScalarStyle)
+                })
+            });
+        }
+        assert_eq!(&writer[..], &b"\"caf\\xE9\"\n"[..]);
+    }
+
+    #[test]
+    #[allow(unused_must_use)]
+    fn emitter_canonical_test() {
+        let options = YamlEmitterOptions { canonical: true, ..Default::default() };
+        let mut writer = Vec::new();
+        {
+            let mut emitter = YamlEmitter::init_with_options(&mut writer, &options);
+            emitter.emit_stream(YamlUtf8Encoding, |e| {
+                e.emit_document(None, &[], true, |e| {
+                    e.emit_scalar_event(None, None, "a", true, false, YamlPlainScalarStyle)
+                })
+            });
+        }
+        assert!(writer.starts_with(b"---"));
+        assert!(writer.ends_with(b"\"a\"\n"));
+    }
 }
@@ -34,7 +34,10 @@ pub struct YamlError {
     pub kind: YamlErrorType,
     pub problem: Option<String>,
     pub io_error: Option<io::Error>,
-    pub context: Option<YamlErrorContext>
+    pub context: Option<YamlErrorContext>,
+    // set when a YamlLimits bound was exceeded, which libyaml itself has no
+    // error type for
+    pub limit_exceeded: bool
 }
 
 impl PartialEq for YamlError {
@@ -44,11 +47,15 @@ impl PartialEq for YamlError {
             && self.io_error.is_none()
             && rhs.io_error.is_none()
             && self.context == rhs.context
+            && self.limit_exceeded == rhs.limit_exceeded
     }
 }
 
 impl Error for YamlError {
     fn description(&self) -> &str {
+        if self.limit_exceeded {
+            return "A configured loading limit was exceeded";
+        }
         match self.kind {
             YamlErrorType::YAML_NO_ERROR => "No error is produced",
             YamlErrorType::YAML_MEMORY_ERROR => "Cannot allocate or reallocate a block of memory",
@@ -75,7 +82,8 @@ impl YamlError {
             kind: kind,
             problem: problem,
             io_error: None,
-            context: None
+            context: None,
+            limit_exceeded: false
         }
     }
 }
@@ -14,7 +14,7 @@ pub struct YamlVersionDirective {
     pub minor: isize,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct YamlTagDirective {
     pub handle: String,
     pub prefix: String,
@@ -31,7 +31,7 @@ impl YamlTagDirective {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct YamlSequenceParam {
     pub anchor: Option<String>,
     pub tag: Option<String>,
@@ -39,7 +39,7 @@ pub struct YamlSequenceParam {
     pub style: YamlSequenceStyle
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct YamlScalarParam {
     pub anchor: Option<String>,
     pub tag: Option<String>,
@@ -49,7 +49,7 @@ pub struct YamlScalarParam {
     pub style: YamlScalarStyle
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum YamlEventSpec {
     YamlNoEvent,
     YamlStreamStartEvent(YamlEncoding),
@@ -64,7 +64,7 @@ pub enum YamlEventSpec {
     YamlMappingEndEvent,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct YamlEvent {
     pub spec: YamlEventSpec,
     pub start: YamlMark,
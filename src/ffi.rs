@@ -191,7 +191,7 @@ pub struct yaml_parser_t {
     pub context: *const c_char,
     pub context_mark: yaml_mark_t,
 
-    pub read_handler: yaml_read_handler_t,
+    pub read_handler: Option<yaml_read_handler_t>,
     pub read_handler_data: *const c_void,
 
     pub input: yaml_parser_input_t,
@@ -224,7 +224,7 @@ pub struct yaml_parser_t {
     pub document: *const yaml_document_t,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 #[repr(C)]
 #[allow(non_camel_case_types)]
 pub enum yaml_break_t {
@@ -244,7 +244,7 @@ pub struct yaml_emitter_t {
     pub error: YamlErrorType,
     pub problem: *const c_char,
 
-    pub write_handler: yaml_write_handler_t,
+    pub write_handler: Option<yaml_write_handler_t>,
     pub write_handler_data: *const c_void,
 
     pub output: yaml_emitter_output_t,
@@ -403,6 +403,63 @@ impl yaml_event_t {
     }
 }
 
+#[derive(Clone, Copy)]
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct yaml_token_t {
+    pub token_type: yaml_token_type_t,
+    pub data: yaml_token_data_t,
+    pub start_mark: yaml_mark_t,
+    pub end_mark: yaml_mark_t
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct yaml_stream_start_token_t {
+    pub encoding: YamlEncoding
+}
+
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct yaml_alias_token_t {
+    pub value: *const yaml_char_t
+}
+
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct yaml_anchor_token_t {
+    pub value: *const yaml_char_t
+}
+
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct yaml_tag_token_t {
+    pub handle: *const yaml_char_t,
+    pub suffix: *const yaml_char_t
+}
+
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct yaml_scalar_token_t {
+    pub value: *const yaml_char_t,
+    pub length: size_t,
+    pub style: YamlScalarStyle
+}
+
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct yaml_tag_directive_token_t {
+    pub handle: *const yaml_char_t,
+    pub prefix: *const yaml_char_t
+}
+
+impl yaml_token_t {
+    pub unsafe fn delete(&mut self) {
+        yaml_token_delete(self);
+    }
+}
+
 #[derive(Clone, Copy)]
 #[repr(C)]
 #[allow(non_camel_case_types)]
@@ -424,6 +481,7 @@ extern {
     pub fn yaml_get_version_string() -> *const c_char;
     pub fn yaml_get_version(major: *mut c_int, minor: *mut c_int, patch: *mut c_int) -> c_void;
     pub fn yaml_event_delete(event: *mut yaml_event_t) -> c_void;
+    pub fn yaml_token_delete(token: *mut yaml_token_t) -> c_void;
     pub fn yaml_document_initialize(document: *mut yaml_document_t,
         version_directive: *const yaml_version_directive_t,
         tag_directives_start: *const yaml_tag_directive_t,
@@ -439,11 +497,16 @@ extern {
         tag: *const yaml_char_t, style: YamlSequenceStyle) -> c_int;
     pub fn yaml_document_add_mapping(document: *mut yaml_document_t,
         tag: *const yaml_char_t, style: YamlSequenceStyle) -> c_int;
+    pub fn yaml_document_append_sequence_item(document: *mut yaml_document_t,
+        sequence: c_int, item: c_int) -> c_int;
+    pub fn yaml_document_append_mapping_pair(document: *mut yaml_document_t,
+        mapping: c_int, key: c_int, value: c_int) -> c_int;
     pub fn yaml_parser_initialize(parser: *mut yaml_parser_t) -> c_int;
     pub fn yaml_parser_set_encoding(parser: *mut yaml_parser_t, encoding: YamlEncoding) -> c_void;
     pub fn yaml_parser_delete(parser: *mut yaml_parser_t) -> c_void;
     pub fn yaml_parser_set_input_string(parser: *mut yaml_parser_t, input: *const yaml_char_t, size: size_t) -> c_void;
     pub fn yaml_parser_set_input(parser: *mut yaml_parser_t, handler: yaml_read_handler_t, data: *const c_void) -> c_void;
+    pub fn yaml_parser_scan(parser: *mut yaml_parser_t, token: *mut yaml_token_t) -> c_int;
     pub fn yaml_parser_parse(parser: *mut yaml_parser_t, event: *mut yaml_event_t) -> c_int;
     pub fn yaml_parser_load(parser: *mut yaml_parser_t, document: *mut yaml_document_t) -> c_int;
     pub fn yaml_emitter_initialize(emitter: *mut yaml_emitter_t) -> c_int;
@@ -451,6 +514,14 @@ extern {
     pub fn yaml_emitter_delete(emitter: *mut yaml_emitter_t) -> c_void;
     pub fn yaml_emitter_set_output(emitter: *mut yaml_emitter_t, handler: yaml_write_handler_t, data: *const c_void) -> c_void;
     pub fn yaml_emitter_flush(emitter: *mut yaml_emitter_t) -> c_int;
+    pub fn yaml_emitter_set_canonical(emitter: *mut yaml_emitter_t, canonical: c_int) -> c_void;
+    pub fn yaml_emitter_set_indent(emitter: *mut yaml_emitter_t, indent: c_int) -> c_void;
+    pub fn yaml_emitter_set_width(emitter: *mut yaml_emitter_t, width: c_int) -> c_void;
+    pub fn yaml_emitter_set_unicode(emitter: *mut yaml_emitter_t, unicode: c_int) -> c_void;
+    pub fn yaml_emitter_set_break(emitter: *mut yaml_emitter_t, line_break: yaml_break_t) -> c_void;
+    pub fn yaml_emitter_open(emitter: *mut yaml_emitter_t) -> c_int;
+    pub fn yaml_emitter_close(emitter: *mut yaml_emitter_t) -> c_int;
+    pub fn yaml_emitter_dump(emitter: *mut yaml_emitter_t, document: *mut yaml_document_t) -> c_int;
     pub fn yaml_stream_start_event_initialize(event: *mut yaml_event_t, encoding: YamlEncoding) -> c_int;
     pub fn yaml_stream_end_event_initialize(event: *mut yaml_event_t) -> c_int;
     pub fn yaml_document_start_event_initialize(event: *mut yaml_event_t,
@@ -5,22 +5,46 @@ extern crate libc;
 
 extern crate regex;
 
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+
+#[cfg(feature = "async")]
+extern crate futures;
+
 use std::str;
 use std::ffi::CStr;
-use std::io::Read;
+use std::io::{Read, Write};
+
+use parser::{YamlParser, YamlDocumentStream, YamlByteParser, YamlIoParser};
+use constructor::{YamlStandardData, YamlStandardConstructor, YamlConstructor, YamlConstructorOptions};
+use representer::YamlStandardRepresenter;
+use emitter::YamlEmitterOptions;
+use error::{YamlError, YamlErrorContext, YamlMark};
 
-use parser::YamlParser;
-use constructor::{YamlStandardData, YamlStandardConstructor, YamlConstructor};
-use error::YamlError;
+#[cfg(feature = "async")]
+use futures::future::Future;
+#[cfg(feature = "async")]
+use futures::io::AsyncRead;
+#[cfg(feature = "async")]
+use futures::stream::{StreamExt, TryStreamExt};
 
 pub mod ffi;
 pub mod error;
 pub mod event;
+pub mod token;
 pub mod parser;
 pub mod emitter;
 pub mod document;
 pub mod codecs;
 pub mod constructor;
+pub mod representer;
+#[cfg(feature = "serde")]
+pub mod deserializer;
+#[cfg(feature = "serde")]
+pub mod serializer;
+#[cfg(feature = "async")]
+pub mod async_parser;
 
 mod type_size;
 
@@ -51,12 +75,12 @@ pub fn parse_bytes_utf8(bytes: &[u8]) -> Result<Vec<YamlStandardData>, YamlError
 }
 
 pub fn parse_bytes(bytes: &[u8], encoding: ffi::YamlEncoding) -> Result<Vec<YamlStandardData>, YamlError> {
-    let parser = parser::YamlByteParser::init(bytes, encoding);
-    let ctor = YamlStandardConstructor::new();
+    parse_bytes_with_options(bytes, encoding, &Default::default())
+}
 
-    parser.load().map(|doc_res|
-        doc_res.and_then(|doc| ctor.construct(doc.root().unwrap()))
-    ).collect()
+pub fn parse_bytes_with_options(bytes: &[u8], encoding: ffi::YamlEncoding,
+        options: &YamlConstructorOptions) -> Result<Vec<YamlStandardData>, YamlError> {
+    load_all_bytes_with_options(bytes, encoding, options).collect()
 }
 
 pub fn parse_io_utf8(reader: &mut Read) -> Result<Vec<YamlStandardData>, YamlError> {
@@ -64,12 +88,154 @@ pub fn parse_io_utf8(reader: &mut Read) -> Result<Vec<YamlStandardData>, YamlErr
 }
 
 pub fn parse_io(reader: &mut Read, encoding: ffi::YamlEncoding) -> Result<Vec<YamlStandardData>, YamlError> {
-    let parser = parser::YamlIoParser::init(reader, encoding);
-    let ctor = YamlStandardConstructor::new();
+    parse_io_with_options(reader, encoding, &Default::default())
+}
+
+pub fn parse_io_with_options(reader: &mut Read, encoding: ffi::YamlEncoding,
+        options: &YamlConstructorOptions) -> Result<Vec<YamlStandardData>, YamlError> {
+    load_all_io_with_options(reader, encoding, options).collect()
+}
+
+// Constructs the documents of a stream one at a time. The iterator ends after
+// the first error from the parser, but not after an error constructing a
+// document.
+pub struct YamlStandardDocuments<P> {
+    docs: YamlDocumentStream<P>,
+    ctor: YamlStandardConstructor,
+    failed: bool
+}
+
+impl<P: YamlParser> YamlStandardDocuments<P> {
+    pub fn new(parser: Box<P>, options: &YamlConstructorOptions) -> YamlStandardDocuments<P> {
+        YamlStandardDocuments {
+            docs: parser.load_with_limits(options.limits),
+            ctor: YamlStandardConstructor::with_options(options),
+            failed: false
+        }
+    }
+
+    // constructs the only document of the stream, and fails if there is none
+    // or more than one
+    pub fn one(mut self) -> Result<YamlStandardData, YamlError> {
+        let first = match self.docs.next() {
+            None => return Err(YamlError::new(ffi::YamlErrorType::YAML_COMPOSER_ERROR,
+                Some("expected a single document in the stream, but found none".to_string()))),
+            Some(res) => try!(res)
+        };
+        let data = try!(self.ctor.construct(first.root().unwrap()));
+
+        match self.docs.next() {
+            None => Ok(data),
+            Some(Err(e)) => Err(e),
+            Some(Ok(second)) => Err(another_document_error(&first.start_mark(), &second.start_mark()))
+        }
+    }
+}
+
+fn another_document_error(first: &YamlMark, second: &YamlMark) -> YamlError {
+    let context = YamlErrorContext {
+        byte_offset: second.index,
+        problem_mark: *second,
+        context: Some("expected a single document in the stream".to_string()),
+        context_mark: *first,
+    };
+
+    YamlError {
+        kind: ffi::YamlErrorType::YAML_COMPOSER_ERROR,
+        problem: Some("but found another document".to_string()),
+        io_error: None,
+        context: Some(context),
+        limit_exceeded: false
+    }
+}
+
+impl<P: YamlParser> Iterator for YamlStandardDocuments<P> {
+    type Item = Result<YamlStandardData, YamlError>;
 
-    parser.load().map(|doc_res|
+    fn next(&mut self) -> Option<Result<YamlStandardData, YamlError>> {
+        if self.failed {
+            return None;
+        }
+
+        match self.docs.next() {
+            None => None,
+            Some(Ok(doc)) => Some(self.ctor.construct(doc.root().unwrap())),
+            Some(Err(e)) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+pub fn load_all_bytes(bytes: &[u8], encoding: ffi::YamlEncoding) -> YamlStandardDocuments<YamlByteParser> {
+    load_all_bytes_with_options(bytes, encoding, &Default::default())
+}
+
+pub fn load_all_bytes_with_options<'r>(bytes: &'r [u8], encoding: ffi::YamlEncoding,
+        options: &YamlConstructorOptions) -> YamlStandardDocuments<YamlByteParser<'r>> {
+    YamlStandardDocuments::new(YamlByteParser::init(bytes, encoding), options)
+}
+
+pub fn load_all_io(reader: &mut Read, encoding: ffi::YamlEncoding) -> YamlStandardDocuments<YamlIoParser> {
+    load_all_io_with_options(reader, encoding, &Default::default())
+}
+
+pub fn load_all_io_with_options<'r>(reader: &'r mut Read, encoding: ffi::YamlEncoding,
+        options: &YamlConstructorOptions) -> YamlStandardDocuments<YamlIoParser<'r>> {
+    YamlStandardDocuments::new(YamlIoParser::init(reader, encoding), options)
+}
+
+pub fn load_one_bytes(bytes: &[u8], encoding: ffi::YamlEncoding) -> Result<YamlStandardData, YamlError> {
+    load_all_bytes(bytes, encoding).one()
+}
+
+pub fn load_one_io(reader: &mut Read, encoding: ffi::YamlEncoding) -> Result<YamlStandardData, YamlError> {
+    load_all_io(reader, encoding).one()
+}
+
+#[cfg(feature = "async")]
+pub fn parse_io_async<R: AsyncRead + Unpin>(reader: R, encoding: ffi::YamlEncoding)
+        -> impl Future<Output=Result<Vec<YamlStandardData>, YamlError>> {
+    parse_io_async_with_options(reader, encoding, &Default::default())
+}
+
+#[cfg(feature = "async")]
+pub fn parse_io_async_with_options<R: AsyncRead + Unpin>(reader: R, encoding: ffi::YamlEncoding,
+        options: &YamlConstructorOptions) -> impl Future<Output=Result<Vec<YamlStandardData>, YamlError>> {
+    let parser = async_parser::YamlAsyncParser::init(reader, encoding);
+    let ctor = YamlStandardConstructor::with_options(options);
+
+    parser.load_with_limits(options.limits).map(move |doc_res|
         doc_res.and_then(|doc| ctor.construct(doc.root().unwrap()))
-    ).collect()
+    ).try_collect()
+}
+
+pub fn to_writer_utf8(writer: &mut Write, docs: &[YamlStandardData]) -> Result<(), YamlError> {
+    to_writer(writer, docs, ffi::YamlEncoding::YamlUtf8Encoding)
+}
+
+pub fn to_writer(writer: &mut Write, docs: &[YamlStandardData], encoding: ffi::YamlEncoding) -> Result<(), YamlError> {
+    to_writer_with_options(writer, docs, encoding, &Default::default())
+}
+
+pub fn to_writer_with_options(writer: &mut Write, docs: &[YamlStandardData], encoding: ffi::YamlEncoding,
+        options: &YamlEmitterOptions) -> Result<(), YamlError> {
+    let mut emitter = emitter::YamlEmitter::init_with_options(writer, options);
+    let repr = YamlStandardRepresenter::new();
+
+    emitter.emit_stream(encoding, |stream| {
+        for doc in docs.iter() {
+            try!(stream.emit_document(None, &[], true, |e| repr.represent(e, doc)));
+        }
+        Ok(())
+    })
+}
+
+pub fn to_string(docs: &[YamlStandardData]) -> Result<String, YamlError> {
+    let mut buf = Vec::new();
+    try!(to_writer_utf8(&mut buf, docs));
+    Ok(String::from_utf8(buf).unwrap())
 }
 
 #[cfg(test)]
@@ -105,6 +271,11 @@ mod test {
         assert_eq!(super::type_size::YAML_EMITTER_T_SIZE, mem::size_of::<super::ffi::yaml_emitter_t>())
     }
 
+    #[test]
+    fn test_token_size() {
+        assert_eq!(super::type_size::YAML_TOKEN_T_SIZE, mem::size_of::<super::ffi::yaml_token_t>())
+    }
+
     #[test]
     fn test_document_size() {
         assert_eq!(super::type_size::YAML_DOCUMENT_T_SIZE, mem::size_of::<super::ffi::yaml_document_t>())
@@ -127,4 +298,113 @@ mod test {
         let mut reader = BufReader::new(data.as_bytes());
         assert_eq!(Ok(vec![YamlSequence(vec![YamlInteger(1), YamlInteger(2), YamlInteger(3)])]), super::parse_io_utf8(&mut reader))
     }
+
+    #[test]
+    fn test_parse_with_options() {
+        let data = "{country: NO, country: SE}";
+        let options = super::constructor::YamlConstructorOptions {
+            schema: super::constructor::YamlSchema::YamlCoreSchema,
+            unique_keys: true,
+            ..Default::default()
+        };
+        assert!(super::parse_bytes_with_options(data.as_bytes(), super::ffi::YamlEncoding::YamlUtf8Encoding, &options).is_err());
+
+        let mut reader = BufReader::new("{country: NO}".as_bytes());
+        assert_eq!(Ok(vec![YamlMapping(vec![(YamlString("country".to_string()), YamlString("NO".to_string()))])]),
+            super::parse_io_with_options(&mut reader, super::ffi::YamlEncoding::YamlUtf8Encoding, &options))
+    }
+
+    #[test]
+    fn test_billion_laughs() {
+        let data = "a: &a [lol, lol, lol, lol, lol, lol, lol, lol, lol]
+b: &b [*a, *a, *a, *a, *a, *a, *a, *a, *a]
+c: &c [*b, *b, *b, *b, *b, *b, *b, *b, *b]
+d: &d [*c, *c, *c, *c, *c, *c, *c, *c, *c]
+e: &e [*d, *d, *d, *d, *d, *d, *d, *d, *d]
+f: &f [*e, *e, *e, *e, *e, *e, *e, *e, *e]
+g: &g [*f, *f, *f, *f, *f, *f, *f, *f, *f]
+h: &h [*g, *g, *g, *g, *g, *g, *g, *g, *g]
+i: &i [*h, *h, *h, *h, *h, *h, *h, *h, *h]
+";
+        let mut options = super::constructor::YamlConstructorOptions::default();
+        options.limits.max_nodes = Some(100000);
+        let err = super::parse_bytes_with_options(data.as_bytes(), super::ffi::YamlEncoding::YamlUtf8Encoding, &options).unwrap_err();
+        assert!(err.limit_exceeded);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_parse_io_async() {
+        use futures::executor::block_on;
+        use futures::io::Cursor;
+
+        let data = "- 1\n- [a, b]\n--- {c: 2.5}\n";
+        let expected = super::parse_bytes_utf8(data.as_bytes());
+        let reader = Cursor::new(data.as_bytes());
+        assert_eq!(expected, block_on(super::parse_io_async(reader, super::ffi::YamlEncoding::YamlUtf8Encoding)));
+
+        let mut options = super::constructor::YamlConstructorOptions::default();
+        options.limits.max_depth = Some(1);
+        let reader = Cursor::new(data.as_bytes());
+        let err = block_on(super::parse_io_async_with_options(reader, super::ffi::YamlEncoding::YamlUtf8Encoding, &options)).unwrap_err();
+        assert!(err.limit_exceeded);
+    }
+
+    #[test]
+    fn test_load_all() {
+        let data = "- 1\n--- {a: b}\n--- [unclosed\n--- 3\n";
+        let mut docs = super::load_all_bytes(data.as_bytes(), super::ffi::YamlEncoding::YamlUtf8Encoding);
+        assert_eq!(Some(Ok(YamlSequence(vec![YamlInteger(1)]))), docs.next());
+        assert_eq!(Some(Ok(YamlMapping(vec![(YamlString("a".to_string()), YamlString("b".to_string()))]))), docs.next());
+        assert_eq!(super::ffi::YamlErrorType::YAML_PARSER_ERROR, docs.next().unwrap().unwrap_err().kind);
+        assert_eq!(None, docs.next());
+
+        let mut reader = BufReader::new("1\n--- 2\n".as_bytes());
+        let docs: Vec<_> = super::load_all_io(&mut reader, super::ffi::YamlEncoding::YamlUtf8Encoding).collect();
+        assert_eq!(vec![Ok(YamlInteger(1)), Ok(YamlInteger(2))], docs);
+    }
+
+    #[test]
+    fn test_load_one() {
+        let utf8 = super::ffi::YamlEncoding::YamlUtf8Encoding;
+        assert_eq!(Ok(YamlInteger(1)), super::load_one_bytes(b"--- 1\n...\n", utf8));
+
+        let mut reader = BufReader::new("[a]".as_bytes());
+        assert_eq!(Ok(YamlSequence(vec![YamlString("a".to_string())])), super::load_one_io(&mut reader, utf8));
+
+        let err = super::load_one_bytes(b"# only a comment\n", utf8).unwrap_err();
+        assert_eq!(super::ffi::YamlErrorType::YAML_COMPOSER_ERROR, err.kind);
+
+        let err = super::load_one_bytes(b"a\n--- b\n", utf8).unwrap_err();
+        assert_eq!(Some("but found another document".to_string()), err.problem);
+        let context = err.context.unwrap();
+        assert_eq!(super::error::YamlMark { index: 0, line: 0, column: 0 }, context.context_mark);
+        assert_eq!(super::error::YamlMark { index: 2, line: 1, column: 0 }, context.problem_mark);
+    }
+
+    #[test]
+    fn test_to_string() {
+        let docs = vec![
+            YamlSequence(vec![YamlInteger(1), YamlString("2".to_string())]),
+            YamlMapping(vec![(YamlString("a".to_string()), YamlNull)])
+        ];
+        assert_eq!(Ok("- 1\n- '2'\n---\na: null\n".to_string()), super::to_string(&docs))
+    }
+
+    #[test]
+    fn test_to_string_round_trip() {
+        let data = "{a: [1, 2.5, 'yes', ~], 'true': '0o17'}";
+        let docs = super::parse_bytes_utf8(data.as_bytes()).unwrap();
+        let text = super::to_string(&docs).unwrap();
+        assert_eq!(Ok(docs), super::parse_bytes_utf8(text.as_bytes()))
+    }
+
+    #[test]
+    fn test_to_writer_with_options() {
+        let docs = vec![YamlMapping(vec![(YamlString("a".to_string()), YamlMapping(vec![(YamlString("b".to_string()), YamlInteger(1))]))])];
+        let options = super::emitter::YamlEmitterOptions { indent: 4, ..Default::default() };
+        let mut writer = Vec::new();
+        super::to_writer_with_options(&mut writer, &docs, super::ffi::YamlEncoding::YamlUtf8Encoding, &options).unwrap();
+        assert_eq!(&b"a:\n    b: 1\n"[..], &writer[..]);
+    }
 }
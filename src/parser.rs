@@ -3,6 +3,7 @@ use libc;
 use ffi;
 use error::{YamlError, YamlErrorContext, YamlMark};
 use event::{YamlEvent, YamlEventSpec};
+use token::{YamlToken, YamlTokenSpec};
 use document::{YamlDocument};
 use codecs;
 
@@ -10,6 +11,10 @@ use std::mem;
 use std::io;
 use std::io::Read;
 use std::slice;
+use std::sync::mpsc;
+use std::sync::mpsc::{Sender, Receiver, TryRecvError};
+use std::thread;
+use std::ops::Range;
 use std::marker::PhantomData;
 
 pub struct YamlEventStream<P> {
@@ -32,8 +37,44 @@ impl<P:YamlParser> Iterator for YamlEventStream<P> {
     }
 }
 
+pub struct YamlTokenStream<P> {
+    parser: Box<P>,
+}
+
+impl<P:YamlParser> Iterator for YamlTokenStream<P> {
+    type Item = Result<YamlToken, YamlError>;
+
+    fn next(&mut self) -> Option<Result<YamlToken, YamlError>> {
+        unsafe {
+            match self.parser.scan_token() {
+                Some(tok) => match tok.spec {
+                    YamlTokenSpec::YamlNoToken => None,
+                    _ => Some(Ok(tok))
+                },
+                None => Some(Err(self.parser.get_error()))
+            }
+        }
+    }
+}
+
+// Bounds on the resources a single document may use, both while it is loaded
+// and while it is constructed. None means unlimited, which is the default.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct YamlLimits {
+    // how deeply collections may be nested
+    pub max_depth: Option<usize>,
+    // nodes in a document; during construction, the nodes reached through an
+    // alias count again every time
+    pub max_nodes: Option<usize>,
+    pub max_alias_dereferences: Option<usize>,
+    // in bytes
+    pub max_scalar_length: Option<usize>,
+    pub max_document_bytes: Option<usize>,
+}
+
 pub struct YamlDocumentStream<P> {
     parser: Box<P>,
+    limits: YamlLimits
 }
 
 impl<P:YamlParser> Iterator for YamlDocumentStream<P> {
@@ -41,13 +82,17 @@ impl<P:YamlParser> Iterator for YamlDocumentStream<P> {
 
     fn next(&mut self) -> Option<Result<Box<YamlDocument>, YamlError>> {
         unsafe {
-            match YamlDocument::parser_load(&mut self.parser.base_parser_ref().parser_mem) {
-                Some(doc) => if doc.is_empty() {
+            match YamlDocument::parser_load(&mut self.parser.base_parser_ref().parser_mem, &self.limits) {
+                Ok(doc) => if doc.is_empty() {
                     None
                 } else {
                     Some(Ok(doc))
                 },
-                None => Some(Err(self.parser.get_error()))
+                Err(limit_exceeded) => {
+                    let mut err = self.parser.get_error();
+                    err.limit_exceeded = limit_exceeded;
+                    Some(Err(err))
+                }
             }
         }
     }
@@ -65,13 +110,25 @@ impl Drop for InternalEvent {
     }
 }
 
+pub struct InternalToken {
+    token_mem: ffi::yaml_token_t
+}
+
+impl Drop for InternalToken {
+    fn drop(&mut self) {
+        unsafe {
+            self.token_mem.delete()
+        }
+    }
+}
+
 pub trait YamlParser: Sized {
     unsafe fn base_parser_ref<'r>(&'r mut self) -> &'r mut YamlBaseParser;
     unsafe fn get_error(&mut self) -> YamlError;
 
     unsafe fn parse_event(&mut self) -> Option<YamlEvent> {
         let mut event = InternalEvent {
-            event_mem: mem::uninitialized()
+            event_mem: mem::zeroed()
         };
 
         if !self.base_parser_ref().parse(&mut event.event_mem) {
@@ -81,6 +138,24 @@ pub trait YamlParser: Sized {
         }
     }
 
+    unsafe fn scan_token(&mut self) -> Option<YamlToken> {
+        let mut token = InternalToken {
+            token_mem: mem::zeroed()
+        };
+
+        if !self.base_parser_ref().scan(&mut token.token_mem) {
+            None
+        } else {
+            Some(YamlToken::load(&token.token_mem))
+        }
+    }
+
+    fn scan(self: Box<Self>) -> YamlTokenStream<Self> {
+        YamlTokenStream {
+            parser: self,
+        }
+    }
+
     fn parse(self: Box<Self>) -> YamlEventStream<Self> {
         YamlEventStream {
             parser: self,
@@ -88,26 +163,45 @@ pub trait YamlParser: Sized {
     }
 
     fn load(self: Box<Self>) -> YamlDocumentStream<Self> {
+        self.load_with_limits(Default::default())
+    }
+
+    fn load_with_limits(self: Box<Self>, limits: YamlLimits) -> YamlDocumentStream<Self> {
         YamlDocumentStream {
             parser: self,
+            limits: limits
+        }
+    }
+}
+
+unsafe fn read_input(reader: &mut Read, io_error: &mut Option<io::Error>,
+        buffer: *mut u8, size: libc::size_t, size_read: *mut libc::size_t) -> libc::c_int {
+    let buf = slice::from_raw_parts_mut(buffer, size as usize);
+    match reader.read(buf) {
+        Ok(size) => {
+            *size_read = size as libc::size_t;
+            return 1;
+        },
+        Err(err) => {
+            *io_error = Some(err);
+            return 0;
         }
     }
 }
 
 extern fn handle_reader_cb(data: *mut YamlIoParser, buffer: *mut u8, size: libc::size_t, size_read: *mut libc::size_t) -> libc::c_int {
     unsafe {
-        let buf = slice::from_raw_parts_mut(buffer, size as usize);
         let parser = &mut *data;
-        match parser.reader.read(buf) {
-            Ok(size) => {
-                *size_read = size as libc::size_t;
-                return 1;
-            },
-            Err(err) => {
-                parser.io_error = Some(err);
-                return 0;
-            }
-        }
+        read_input(parser.reader, &mut parser.io_error, buffer, size, size_read)
+    }
+}
+
+// the read handler type is declared for YamlIoParser, but the data pointer is
+// only ever passed back to the handler
+extern fn handle_owned_reader_cb(data: *mut YamlIoParser, buffer: *mut u8, size: libc::size_t, size_read: *mut libc::size_t) -> libc::c_int {
+    unsafe {
+        let parser = &mut *(data as *mut YamlOwnedIoParser);
+        read_input(&mut *parser.reader, &mut parser.io_error, buffer, size, size_read)
     }
 }
 
@@ -118,7 +212,7 @@ pub struct YamlBaseParser {
 impl YamlBaseParser {
     unsafe fn new() -> YamlBaseParser {
         YamlBaseParser {
-            parser_mem: mem::uninitialized()
+            parser_mem: mem::zeroed()
         }
     }
 
@@ -130,6 +224,10 @@ impl YamlBaseParser {
         ffi::yaml_parser_set_input_string(&mut self.parser_mem, input, size as libc::size_t);
     }
 
+    unsafe fn scan(&mut self, token: &mut ffi::yaml_token_t) -> bool {
+        ffi::yaml_parser_scan(&mut self.parser_mem, token) != 0
+    }
+
     unsafe fn parse(&mut self, event: &mut ffi::yaml_event_t) -> bool {
         ffi::yaml_parser_parse(&mut self.parser_mem, event) != 0
     }
@@ -146,7 +244,8 @@ impl YamlBaseParser {
             kind: self.parser_mem.error,
             problem: codecs::decode_c_str(self.parser_mem.problem as *const ffi::yaml_char_t),
             io_error: None,
-            context: Some(context)
+            context: Some(context),
+            limit_exceeded: false
         }
     }
 }
@@ -234,10 +333,430 @@ impl<'r> YamlIoParser<'r> {
     }
 } 
 
+// Parsers that own their input, so they can be stored and moved across
+// threads. libyaml keeps pointers into the boxed parser and the input, which
+// don't move with the box, and the parser state is only touched through
+// &mut self.
+pub struct YamlOwnedByteParser {
+    base_parser: YamlBaseParser,
+    data: Vec<u8>
+}
+
+unsafe impl Send for YamlOwnedByteParser {}
+
+impl YamlParser for YamlOwnedByteParser {
+    unsafe fn base_parser_ref<'a>(&'a mut self) -> &'a mut YamlBaseParser {
+        &mut self.base_parser
+    }
+
+    unsafe fn get_error(&mut self) -> YamlError {
+        self.base_parser.build_error()
+    }
+}
+
+impl YamlOwnedByteParser {
+    // takes a Vec<u8> or a String
+    pub fn init<T: Into<Vec<u8>>>(data: T, encoding: ffi::YamlEncoding) -> Box<YamlOwnedByteParser> {
+        unsafe {
+            let mut parser = Box::new(YamlOwnedByteParser {
+                base_parser: YamlBaseParser::new(),
+                data: data.into()
+            });
+
+            if !parser.base_parser.initialize() {
+                panic!("failed to initialize yaml_parser_t");
+            }
+
+            ffi::yaml_parser_set_encoding(&mut parser.base_parser.parser_mem, encoding);
+            let (ptr, len) = (parser.data.as_ptr(), parser.data.len());
+            parser.base_parser.set_input_string(ptr, len);
+
+            parser
+        }
+    }
+}
+
+pub struct YamlOwnedIoParser {
+    base_parser: YamlBaseParser,
+    reader: Box<Read + Send>,
+    io_error: Option<io::Error>,
+}
+
+unsafe impl Send for YamlOwnedIoParser {}
+
+impl YamlParser for YamlOwnedIoParser {
+    unsafe fn base_parser_ref<'a>(&'a mut self) -> &'a mut YamlBaseParser {
+        &mut self.base_parser
+    }
+
+    unsafe fn get_error(&mut self) -> YamlError {
+        let mut error = self.base_parser.build_error();
+        mem::swap(&mut (error.io_error), &mut (self.io_error));
+        return error;
+    }
+}
+
+impl YamlOwnedIoParser {
+    pub fn init(reader: Box<Read + Send>, encoding: ffi::YamlEncoding) -> Box<YamlOwnedIoParser> {
+        unsafe {
+            let mut parser = Box::new(YamlOwnedIoParser {
+                base_parser: YamlBaseParser::new(),
+                reader: reader,
+                io_error: None
+            });
+
+            if !parser.base_parser.initialize() {
+                panic!("failed to initialize yaml_parser_t");
+            }
+
+            ffi::yaml_parser_set_encoding(&mut parser.base_parser.parser_mem, encoding);
+
+            let data = &mut *parser as *mut YamlOwnedIoParser as *const libc::c_void;
+            ffi::yaml_parser_set_input(&mut parser.base_parser.parser_mem, handle_owned_reader_cb, data);
+
+            parser
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct YamlDocumentRange {
+    pub start: YamlMark,
+    pub end: YamlMark,
+    // the bytes of the input between the marks
+    pub bytes: Range<usize>
+}
+
+// Splits a stream into the source text of each document while parsing it.
+// A document reaches up to its "..." marker or the start of the next document,
+// and starts where the one before it ended, so that comments before a document
+// stay with it. The last document reaches to the end of the input, and the
+// ranges together make up the whole input.
+pub struct YamlDocumentSplitter<'r> {
+    input: &'r [u8],
+    events: YamlEventStream<YamlByteParser<'r>>,
+    encoding: ffi::YamlEncoding,
+    // the character index and byte offset of the last converted mark
+    position: (usize, usize),
+    // the mark and byte offset where the last document ended
+    last_end: (YamlMark, usize),
+    peeked: Option<Result<YamlEvent, YamlError>>,
+    failed: bool
+}
+
+impl<'r> YamlDocumentSplitter<'r> {
+    pub fn init(input: &'r [u8], encoding: ffi::YamlEncoding) -> YamlDocumentSplitter<'r> {
+        YamlDocumentSplitter {
+            input: input,
+            events: YamlByteParser::init(input, encoding).parse(),
+            encoding: encoding,
+            position: (0, 0),
+            last_end: (YamlMark { index: 0, line: 0, column: 0 }, 0),
+            peeked: None,
+            failed: false
+        }
+    }
+
+    // marks only move forward through the stream
+    fn byte_offset(&mut self, mark: &YamlMark) -> usize {
+        let (index, offset) = self.position;
+        let offset = advance_chars(self.input, self.encoding, offset, mark.index - index);
+        self.position = (mark.index, offset);
+        offset
+    }
+}
+
+impl<'r> Iterator for YamlDocumentSplitter<'r> {
+    type Item = Result<YamlDocumentRange, YamlError>;
+
+    fn next(&mut self) -> Option<Result<YamlDocumentRange, YamlError>> {
+        while !self.failed {
+            let next = match self.peeked.take() {
+                Some(peeked) => Some(peeked),
+                None => self.events.next()
+            };
+            let event = match next {
+                None => return None,
+                Some(Ok(event)) => event,
+                Some(Err(e)) => {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+            };
+
+            match event.spec {
+                YamlEventSpec::YamlStreamStartEvent(encoding) => self.encoding = encoding,
+                YamlEventSpec::YamlDocumentEndEvent(_) => {
+                    let mut end = (event.end, self.byte_offset(&event.end));
+                    // whatever follows the last document belongs to it
+                    match self.events.next() {
+                        Some(Ok(ref next)) if next.spec == YamlEventSpec::YamlStreamEndEvent =>
+                            end = (next.end, self.input.len()),
+                        next => self.peeked = next
+                    }
+
+                    let (start, start_offset) = self.last_end;
+                    self.last_end = end;
+                    return Some(Ok(YamlDocumentRange {
+                        start: start,
+                        end: end.0,
+                        bytes: start_offset..end.1
+                    }));
+                },
+                _ => ()
+            }
+        }
+        None
+    }
+}
+
+// Moves the given number of characters forward from a byte offset into the
+// input. Marks don't count the BOM, so it is skipped at the start.
+fn advance_chars(input: &[u8], encoding: ffi::YamlEncoding, mut offset: usize, chars: usize) -> usize {
+    match encoding {
+        ffi::YamlEncoding::YamlUtf16LeEncoding | ffi::YamlEncoding::YamlUtf16BeEncoding => {
+            let le = encoding == ffi::YamlEncoding::YamlUtf16LeEncoding;
+            if offset == 0 && input.starts_with(if le { b"\xff\xfe" } else { b"\xfe\xff" }) {
+                offset = 2;
+            }
+            for _ in 0..chars {
+                let unit = if le { input[offset + 1] } else { input[offset] };
+                // a surrogate pair is a single character
+                offset += if unit & 0xfc == 0xd8 { 4 } else { 2 };
+            }
+        },
+        _ => {
+            if offset == 0 && input.starts_with(b"\xef\xbb\xbf") {
+                offset = 3;
+            }
+            for _ in 0..chars {
+                let lead = input[offset];
+                offset += if lead < 0x80 { 1 } else if lead < 0xe0 { 2 } else if lead < 0xf0 { 3 } else { 4 };
+            }
+        }
+    }
+    offset
+}
+
+// Messages from the thread behind a push parser
+enum YamlPushMessage<T> {
+    YamlPushItem(Result<T, YamlError>),
+    // every chunk received so far has been read, counting the end of the input
+    YamlPushStarved(usize)
+}
+
+// The reader of the thread behind a push parser. When it runs out of input,
+// it tells the push parser and waits for the next chunk, so libyaml stays
+// where it was in the middle of the input.
+struct YamlPushReader<T> {
+    chunks: Receiver<Option<Vec<u8>>>,
+    output: Sender<YamlPushMessage<T>>,
+    chunk: Vec<u8>,
+    pos: usize,
+    received: usize,
+    finished: bool
+}
+
+impl<T> Read for YamlPushReader<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.chunk.len() && !self.finished {
+            let next = match self.chunks.try_recv() {
+                Ok(next) => Ok(next),
+                Err(TryRecvError::Empty) => {
+                    let _ = self.output.send(YamlPushMessage::YamlPushStarved(self.received));
+                    self.chunks.recv().map_err(|_| ())
+                },
+                Err(TryRecvError::Disconnected) => Err(())
+            };
+            match next {
+                Ok(Some(chunk)) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
+                },
+                Ok(None) => self.finished = true,
+                Err(()) => return Err(io::Error::new(io::ErrorKind::BrokenPipe, "the push parser was dropped"))
+            }
+            self.received += 1;
+        }
+
+        let size = try!((&self.chunk[self.pos..]).read(buf));
+        self.pos += size;
+        Ok(size)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum YamlPushResult<T> {
+    YamlReady(T),
+    // everything complete has been returned, and the rest of the stream
+    // needs more input
+    YamlNeedMoreInput,
+    YamlStreamFinished
+}
+
+// The side of a push parser that the chunks are fed to.
+//
+// libyaml pulls its input through a read handler and can't return from the
+// middle of it, so a single libyaml parser runs on a thread of its own, where
+// the read handler waits for the next chunk. Every byte is parsed once, and
+// whatever libyaml produces comes back in order as soon as it is complete.
+struct YamlPushInput<T> {
+    chunks: Sender<Option<Vec<u8>>>,
+    output: Receiver<YamlPushMessage<T>>,
+    // the number of chunks sent, counting the end of the input
+    sent: usize,
+    // set while the thread waits for input that hasn't been fed yet
+    starved: bool,
+    finished: bool
+}
+
+impl<T: Send + 'static> YamlPushInput<T> {
+    fn spawn<F>(encoding: ffi::YamlEncoding, run: F) -> YamlPushInput<T>
+        where F: FnOnce(Box<YamlOwnedIoParser>, &Sender<YamlPushMessage<T>>) + Send + 'static
+    {
+        let (chunk_tx, chunk_rx) = mpsc::channel();
+        let (output_tx, output_rx) = mpsc::channel();
+        let reader = YamlPushReader {
+            chunks: chunk_rx,
+            output: output_tx.clone(),
+            chunk: Vec::new(),
+            pos: 0,
+            received: 0,
+            finished: false
+        };
+        thread::spawn(move || {
+            let parser = YamlOwnedIoParser::init(Box::new(reader), encoding);
+            run(parser, &output_tx);
+        });
+
+        YamlPushInput {
+            chunks: chunk_tx,
+            output: output_rx,
+            sent: 0,
+            starved: false,
+            finished: false
+        }
+    }
+
+    fn feed(&mut self, data: &[u8]) {
+        assert!(!self.finished, "cannot feed a finished push parser");
+        if !data.is_empty() {
+            self.send(Some(data.to_vec()));
+        }
+    }
+
+    fn finish(&mut self) {
+        if !self.finished {
+            self.finished = true;
+            self.send(None);
+        }
+    }
+
+    fn send(&mut self, chunk: Option<Vec<u8>>) {
+        // the thread is gone once the stream has ended or failed, and has no
+        // use for the chunk
+        let _ = self.chunks.send(chunk);
+        self.sent += 1;
+        self.starved = false;
+    }
+
+    fn next(&mut self) -> Result<YamlPushResult<T>, YamlError> {
+        while !self.starved {
+            match self.output.recv() {
+                Ok(YamlPushMessage::YamlPushItem(res)) => return res.map(YamlPushResult::YamlReady),
+                // the thread may have run out just before the latest chunks
+                // arrived, and reads them next
+                Ok(YamlPushMessage::YamlPushStarved(received)) => self.starved = received == self.sent,
+                Err(_) => return Ok(YamlPushResult::YamlStreamFinished)
+            }
+        }
+        Ok(YamlPushResult::YamlNeedMoreInput)
+    }
+}
+
+// forwards the results of a parser thread until the first error
+fn send_all<T, I: Iterator<Item=Result<T, YamlError>>>(results: I, output: &Sender<YamlPushMessage<T>>) {
+    for res in results {
+        let failed = res.is_err();
+        if output.send(YamlPushMessage::YamlPushItem(res)).is_err() || failed {
+            break;
+        }
+    }
+}
+
+// An event parser for input that arrives in chunks. Each event is returned as
+// soon as the input fed so far completes it.
+pub struct YamlPushParser {
+    input: YamlPushInput<YamlEvent>
+}
+
+impl YamlPushParser {
+    pub fn new(encoding: ffi::YamlEncoding) -> YamlPushParser {
+        YamlPushParser {
+            input: YamlPushInput::spawn(encoding, |parser, output| send_all(parser.parse(), output))
+        }
+    }
+
+    pub fn feed(&mut self, data: &[u8]) {
+        self.input.feed(data);
+    }
+
+    // marks the end of the input
+    pub fn finish(&mut self) {
+        self.input.finish();
+    }
+
+    pub fn next_event(&mut self) -> Result<YamlPushResult<YamlEvent>, YamlError> {
+        self.input.next()
+    }
+
+    // returns every event that is complete with the input fed so far
+    pub fn drain(&mut self) -> Result<Vec<YamlEvent>, YamlError> {
+        let mut events = Vec::new();
+        while let YamlPushResult::YamlReady(event) = try!(self.next_event()) {
+            events.push(event);
+        }
+        Ok(events)
+    }
+}
+
+// A document loader for input that arrives in chunks. Each document is
+// returned as soon as the input fed so far completes it.
+pub struct YamlPushLoader {
+    input: YamlPushInput<Box<YamlDocument>>
+}
+
+impl YamlPushLoader {
+    pub fn new(encoding: ffi::YamlEncoding) -> YamlPushLoader {
+        YamlPushLoader::with_limits(encoding, Default::default())
+    }
+
+    pub fn with_limits(encoding: ffi::YamlEncoding, limits: YamlLimits) -> YamlPushLoader {
+        YamlPushLoader {
+            input: YamlPushInput::spawn(encoding, move |parser, output| send_all(parser.load_with_limits(limits), output))
+        }
+    }
+
+    pub fn feed(&mut self, data: &[u8]) {
+        self.input.feed(data);
+    }
+
+    // marks the end of the input
+    pub fn finish(&mut self) {
+        self.input.finish();
+    }
+
+    pub fn next_document(&mut self) -> Result<YamlPushResult<Box<YamlDocument>>, YamlError> {
+        self.input.next()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use event::{YamlEventSpec, YamlSequenceParam, YamlScalarParam};
     use event::YamlEventSpec::*;
+    use token::{YamlToken, YamlTokenSpec};
+    use token::YamlTokenSpec::*;
     use document::{YamlDocument, YamlNode};
     use parser;
     use parser::YamlParser;
@@ -247,6 +766,14 @@ mod test {
     use ffi::YamlScalarStyle::*;
     use ffi::YamlSequenceStyle::*;
     use std::io::BufReader;
+    use std::ops::Range;
+    use std::io;
+    use std::thread;
+    use event::YamlEvent;
+    use error::YamlMark;
+    use ffi;
+    use std::mem;
+    use std::ptr;
 
     #[test]
     fn test_byte_parser() {
@@ -313,6 +840,86 @@ mod test {
         assert_eq!(expected, stream);
     }
 
+    #[test]
+    fn test_scanner() {
+        let data = "[1, 2, 3]";
+        let parser = parser::YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let expected = Ok(vec![
+            YamlStreamStartToken(YamlUtf8Encoding),
+            YamlFlowSequenceStartToken,
+            YamlScalarToken("1".to_string(), YamlPlainScalarStyle),
+            YamlFlowEntryToken,
+            YamlScalarToken("2".to_string(), YamlPlainScalarStyle),
+            YamlFlowEntryToken,
+            YamlScalarToken("3".to_string(), YamlPlainScalarStyle),
+            YamlFlowSequenceEndToken,
+            YamlStreamEndToken
+        ]);
+
+        let stream: Result<Vec<YamlTokenSpec>, YamlError> = parser.scan().map(|res| res.map(|tok| tok.spec)).collect();
+
+        assert_eq!(expected, stream);
+    }
+
+    #[test]
+    fn test_scanner_block_mapping() {
+        let data = "a: &x !!str 'b'\nc:\n  - *x\n";
+        let parser = parser::YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let expected = Ok(vec![
+            YamlStreamStartToken(YamlUtf8Encoding),
+            YamlBlockMappingStartToken,
+            YamlKeyToken,
+            YamlScalarToken("a".to_string(), YamlPlainScalarStyle),
+            YamlValueToken,
+            YamlAnchorToken("x".to_string()),
+            YamlTagToken("!!".to_string(), "str".to_string()),
+            YamlScalarToken("b".to_string(), YamlSingleQuotedScalarStyle),
+            YamlKeyToken,
+            YamlScalarToken("c".to_string(), YamlPlainScalarStyle),
+            YamlValueToken,
+            YamlBlockSequenceStartToken,
+            YamlBlockEntryToken,
+            YamlAliasToken("x".to_string()),
+            YamlBlockEndToken,
+            YamlBlockEndToken,
+            YamlStreamEndToken
+        ]);
+
+        let stream: Result<Vec<YamlTokenSpec>, YamlError> = parser.scan().map(|res| res.map(|tok| tok.spec)).collect();
+
+        assert_eq!(expected, stream);
+    }
+
+    #[test]
+    fn test_scanner_marks() {
+        let data = "key: 'value'";
+        let parser = parser::YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let scalars: Vec<(usize, usize)> = parser.scan().filter_map(|res| match res {
+            Ok(YamlToken { spec: YamlScalarToken(..), start, end }) => Some((start.index, end.index)),
+            Ok(_) => None,
+            Err(e) => panic!("unexpected result: {:?}", e)
+        }).collect();
+
+        assert_eq!(vec![(0, 3), (5, 12)], scalars);
+    }
+
+    #[test]
+    fn test_scanner_error() {
+        let data = "\"ab";
+        let parser = parser::YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let mut stream = parser.scan();
+
+        match stream.next() {
+            Some(Ok(tok)) => assert_eq!(YamlStreamStartToken(YamlUtf8Encoding), tok.spec),
+            res => panic!("unexpected result: {:?}", res)
+        }
+
+        match stream.next() {
+            Some(Err(err)) => assert_eq!(YamlErrorType::YAML_SCANNER_ERROR, err.kind),
+            tok => panic!("unexpected result: {:?}", tok),
+        }
+    }
+
     #[test]
     fn test_parser_error() {
         let data = "\"ab";
@@ -383,4 +990,302 @@ mod test {
             }
         }
     }
+
+    // loads documents with libyaml's own yaml_parser_load(), returning the
+    // number of documents or the first error
+    fn libyaml_load(data: &str) -> Result<usize, YamlError> {
+        let mut parser = parser::YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+        let mut count = 0;
+        unsafe {
+            loop {
+                let mut document = mem::zeroed();
+                if ffi::yaml_parser_load(&mut parser.base_parser_ref().parser_mem, &mut document) == 0 {
+                    return Err(parser.get_error());
+                }
+                let empty = ffi::yaml_document_get_root_node(&mut document) == ptr::null_mut();
+                ffi::yaml_document_delete(&mut document);
+                if empty {
+                    return Ok(count);
+                }
+                count += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn test_load_matches_libyaml() {
+        let cases = [
+            ("a: &x 1\nb: *x\n--- 2\n", None),
+            ("- &x [*x]\n", None),
+            ("a: &x 1\nb: *y\n", Some("found undefined alias")),
+            ("a: &x 1\n--- [*x]\n", Some("found undefined alias")),
+        ];
+        for &(data, problem) in cases.iter() {
+            let expected = libyaml_load(data);
+            assert_eq!(problem.map(|p| p.to_string()), expected.as_ref().err().and_then(|e| e.problem.clone()));
+
+            let parser = parser::YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding);
+            let docs: Result<Vec<Box<YamlDocument>>, YamlError> = parser.load().collect();
+            assert_eq!(expected, docs.map(|docs| docs.len()));
+        }
+    }
+
+    fn event_summary(events: &[YamlEvent]) -> Vec<(YamlEventSpec, YamlMark, YamlMark)> {
+        events.iter().map(|event| (event.spec.clone(), event.start, event.end)).collect()
+    }
+
+    fn push_bytes(data: &[u8], chunk_size: usize) -> Vec<YamlEvent> {
+        let mut parser = parser::YamlPushParser::new(YamlAnyEncoding);
+        let mut events = Vec::new();
+        for chunk in data.chunks(chunk_size) {
+            parser.feed(chunk);
+            events.extend(parser.drain().unwrap());
+        }
+        parser.finish();
+        events.extend(parser.drain().unwrap());
+        events
+    }
+
+    #[test]
+    fn test_push_parser_chunks() {
+        let data = "%YAML 1.1\n--- {a: é}\n...\n# comment\n--- [x, \"𝄞\"]\n---\n- 1\n- 2\n";
+        let mut utf16 = vec![0xff, 0xfe];
+        for unit in data.encode_utf16() {
+            utf16.push(unit as u8);
+            utf16.push((unit >> 8) as u8);
+        }
+
+        for bytes in [data.as_bytes(), &utf16[..]].iter() {
+            let expected: Vec<YamlEvent> = parser::YamlByteParser::init(bytes, YamlAnyEncoding)
+                .parse().map(|res| res.unwrap()).collect();
+
+            for chunk_size in 1..4 {
+                let events = push_bytes(bytes, chunk_size);
+                assert_eq!(event_summary(&expected), event_summary(&events));
+            }
+        }
+    }
+
+    #[test]
+    fn test_push_parser_need_more_input() {
+        let mut parser = parser::YamlPushParser::new(YamlUtf8Encoding);
+        parser.feed(b"- 1\n- 2");
+        let specs: Vec<YamlEventSpec> = parser.drain().unwrap().into_iter().map(|event| event.spec).collect();
+        assert_eq!(vec![
+            YamlStreamStartEvent(YamlUtf8Encoding),
+            YamlDocumentStartEvent(None, vec![], true),
+            YamlSequenceStartEvent(YamlSequenceParam{anchor: None, tag: None, implicit: true, style: YamlBlockSequenceStyle}),
+            YamlScalarEvent(YamlScalarParam{anchor: None, tag: None, value: "1".to_string(), plain_implicit: true, quoted_implicit: false, style: YamlPlainScalarStyle}),
+        ], specs);
+
+        match parser.next_event() {
+            Ok(parser::YamlPushResult::YamlNeedMoreInput) => (),
+            res => panic!("unexpected result: {:?}", res)
+        }
+
+        parser.feed(b"\n");
+        parser.finish();
+        let specs: Vec<YamlEventSpec> = parser.drain().unwrap().into_iter().map(|event| event.spec).collect();
+        assert_eq!(vec![
+            YamlScalarEvent(YamlScalarParam{anchor: None, tag: None, value: "2".to_string(), plain_implicit: true, quoted_implicit: false, style: YamlPlainScalarStyle}),
+            YamlSequenceEndEvent,
+            YamlDocumentEndEvent(true),
+            YamlStreamEndEvent
+        ], specs);
+
+        match parser.next_event() {
+            Ok(parser::YamlPushResult::YamlStreamFinished) => (),
+            res => panic!("unexpected result: {:?}", res)
+        }
+    }
+
+    #[test]
+    fn test_push_parser_resumes() {
+        let mut parser = parser::YamlPushParser::new(YamlUtf8Encoding);
+        parser.feed(b"[\n  a, b");
+        assert_eq!(3, parser.drain().unwrap().len());
+
+        // the scalars complete without a line break
+        parser.feed(b"c, d, e");
+        let values: Vec<String> = parser.drain().unwrap().into_iter().map(|event| match event.spec {
+            YamlScalarEvent(param) => param.value,
+            spec => panic!("unexpected event: {:?}", spec)
+        }).collect();
+        assert_eq!(vec!["a".to_string(), "bc".to_string()], values);
+
+        // several chunks fed before the next event is asked for
+        parser.feed(b"]");
+        parser.feed(b"\n");
+        parser.finish();
+        assert_eq!(5, parser.drain().unwrap().len());
+        match parser.next_event() {
+            Ok(parser::YamlPushResult::YamlStreamFinished) => (),
+            res => panic!("unexpected result: {:?}", res)
+        }
+    }
+
+    #[test]
+    fn test_push_parser_large_document() {
+        let mut data = String::from("[");
+        for i in 0..20000 {
+            data.push_str(&format!("{}, ", i));
+        }
+        data.push_str("end]\n");
+
+        let mut parser = parser::YamlPushParser::new(YamlUtf8Encoding);
+        let mut count = 0;
+        for chunk in data.as_bytes().chunks(1) {
+            parser.feed(chunk);
+            count += parser.drain().unwrap().len();
+        }
+        parser.finish();
+        count += parser.drain().unwrap().len();
+        assert_eq!(20001 + 6, count);
+    }
+
+    #[test]
+    fn test_push_parser_incomplete_input() {
+        let mut parser = parser::YamlPushParser::new(YamlUtf8Encoding);
+        parser.feed(b"a: 1\n---\nb: [1, 2");
+        assert_eq!(11, parser.drain().unwrap().len());
+        parser.finish();
+
+        let err = parser.drain().unwrap_err();
+        assert_eq!(YamlErrorType::YAML_PARSER_ERROR, err.kind);
+
+        let expected = parser::YamlByteParser::init(b"a: 1\n---\nb: [1, 2", YamlUtf8Encoding)
+            .parse().filter_map(|res| res.err()).next().unwrap();
+        assert_eq!(expected, err);
+    }
+
+    fn load_summary(docs: &[Box<YamlDocument>]) -> Vec<(YamlMark, YamlMark, String)> {
+        docs.iter().map(|doc| {
+            let value = match doc.root() {
+                Some(YamlNode::YamlSequenceNode(seq)) => seq.values().map(|node| match node {
+                    YamlNode::YamlScalarNode(scalar) => scalar.get_value(),
+                    _ => panic!("unexpected node")
+                }).collect::<Vec<String>>().join(","),
+                _ => panic!("unexpected root")
+            };
+            (doc.start_mark(), doc.end_mark(), value)
+        }).collect()
+    }
+
+    #[test]
+    fn test_push_loader() {
+        let data = "- a\n- é\n---\n- &x b\n- *x\n...\n--- [c]\n";
+        let expected: Vec<Box<YamlDocument>> = parser::YamlByteParser::init(data.as_bytes(), YamlUtf8Encoding)
+            .load().map(|res| res.unwrap()).collect();
+
+        let mut loader = parser::YamlPushLoader::new(YamlAnyEncoding);
+        let mut docs = Vec::new();
+        for byte in data.as_bytes().chunks(1) {
+            loader.feed(byte);
+            while let parser::YamlPushResult::YamlReady(doc) = loader.next_document().unwrap() {
+                docs.push(doc);
+            }
+        }
+        assert_eq!(2, docs.len());
+
+        loader.finish();
+        while let parser::YamlPushResult::YamlReady(doc) = loader.next_document().unwrap() {
+            docs.push(doc);
+        }
+        match loader.next_document() {
+            Ok(parser::YamlPushResult::YamlStreamFinished) => (),
+            _ => panic!("expected the end of the stream")
+        }
+
+        assert_eq!(load_summary(&expected), load_summary(&docs));
+    }
+
+    #[test]
+    fn test_push_loader_error() {
+        let mut loader = parser::YamlPushLoader::new(YamlUtf8Encoding);
+        loader.feed(b"[a]\n--- [*x]\n");
+        loader.finish();
+
+        match loader.next_document() {
+            Ok(parser::YamlPushResult::YamlReady(_)) => (),
+            _ => panic!("expected a document")
+        }
+        let err = loader.next_document().err().unwrap();
+        assert_eq!(YamlErrorType::YAML_COMPOSER_ERROR, err.kind);
+        assert_eq!(YamlMark { index: 9, line: 1, column: 5 }, err.context.unwrap().problem_mark);
+    }
+
+    #[test]
+    fn test_owned_parsers() {
+        fn assert_send<T: Send + 'static>(_: &T) {}
+
+        let byte_parser = parser::YamlOwnedByteParser::init("[1, 2, 3]".to_string(), YamlUtf8Encoding);
+        let reader = io::Cursor::new(b"- a\n---\n- b\n".to_vec());
+        let io_parser = parser::YamlOwnedIoParser::init(Box::new(reader), YamlUtf8Encoding);
+        assert_send(&byte_parser);
+        assert_send(&io_parser);
+
+        let events = thread::spawn(move || byte_parser.parse().map(|res| res.unwrap().spec).collect::<Vec<_>>());
+        let docs = thread::spawn(move || io_parser.load().map(|res| res.unwrap().is_empty()).collect::<Vec<_>>());
+        assert_eq!(9, events.join().unwrap().len());
+        assert_eq!(vec![false, false], docs.join().unwrap());
+    }
+
+    #[test]
+    fn test_owned_io_parser_error() {
+        struct FailingReader;
+        impl io::Read for FailingReader {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::Other, "failed"))
+            }
+        }
+
+        let parser = parser::YamlOwnedIoParser::init(Box::new(FailingReader), YamlUtf8Encoding);
+        let err = parser.parse().next().unwrap().unwrap_err();
+        assert_eq!(YamlErrorType::YAML_READER_ERROR, err.kind);
+        assert_eq!(io::ErrorKind::Other, err.io_error.unwrap().kind());
+    }
+
+    #[test]
+    fn test_document_splitter() {
+        let data = "# head\n%YAML 1.1\n--- # first\na: é\n...\n# between\n---\n- 1\n# trailing\n--- [x]\n";
+        let ranges: Vec<parser::YamlDocumentRange> = parser::YamlDocumentSplitter::init(data.as_bytes(), YamlUtf8Encoding)
+            .map(|res| res.unwrap()).collect();
+
+        let texts: Vec<&str> = ranges.iter().map(|range| &data[range.bytes.clone()]).collect();
+        assert_eq!(vec!["# head\n%YAML 1.1\n--- # first\na: é\n...", "\n# between\n---\n- 1\n# trailing\n", "--- [x]\n"], texts);
+        assert_eq!(YamlMark { index: 0, line: 0, column: 0 }, ranges[0].start);
+        assert_eq!(YamlMark { index: 37, line: 4, column: 3 }, ranges[0].end);
+        assert_eq!(ranges[0].end, ranges[1].start);
+    }
+
+    #[test]
+    fn test_document_splitter_comments() {
+        let data = "# leading\na: 1\n...\n# between\n---\nb: 2\n...\n# trailing\n";
+        let texts: Vec<&str> = parser::YamlDocumentSplitter::init(data.as_bytes(), YamlUtf8Encoding)
+            .map(|res| &data[res.unwrap().bytes]).collect();
+        assert_eq!(vec!["# leading\na: 1\n...", "\n# between\n---\nb: 2\n...\n# trailing\n"], texts);
+        assert_eq!(data, texts.concat());
+    }
+
+    #[test]
+    fn test_document_splitter_utf16() {
+        let data = "\u{feff}- 𝄞\n--- b\n";
+        let mut utf16 = Vec::new();
+        for unit in data.encode_utf16() {
+            utf16.push((unit >> 8) as u8);
+            utf16.push(unit as u8);
+        }
+
+        let ranges: Vec<Range<usize>> = parser::YamlDocumentSplitter::init(&utf16[..], YamlAnyEncoding)
+            .map(|res| res.unwrap().bytes).collect();
+        assert_eq!(vec![0..12, 12..24], ranges);
+    }
+
+    #[test]
+    fn test_document_splitter_error() {
+        let mut splitter = parser::YamlDocumentSplitter::init(b"a\n--- [b\n--- c\n", YamlUtf8Encoding);
+        assert_eq!(Some(0..2), splitter.next().map(|res| res.unwrap().bytes));
+        assert_eq!(YamlErrorType::YAML_PARSER_ERROR, splitter.next().unwrap().unwrap_err().kind);
+        assert!(splitter.next().is_none());
+    }
 }
@@ -0,0 +1,255 @@
+use emitter::YamlEmitter;
+use codecs;
+use constructor::{YamlStandardData, YamlStandardConstructor, BINARY_TAG, SET_TAG, OMAP_TAG, PAIRS_TAG};
+use error::YamlError;
+use ffi::{YamlErrorType, YamlScalarStyle, YamlSequenceStyle};
+
+use std::str;
+
+#[derive(Clone)]
+pub struct YamlStandardRepresenter {
+    ctor: YamlStandardConstructor
+}
+
+fn represent_float(f: f64) -> String {
+    if f.is_nan() {
+        ".nan".to_string()
+    } else if f.is_infinite() {
+        if f > 0.0 { ".inf".to_string() } else { "-.inf".to_string() }
+    } else {
+        // Debug formatting always keeps a fraction or an exponent, so the
+        // value is never mistaken for an integer
+        format!("{:?}", f)
+    }
+}
+
+impl YamlStandardRepresenter {
+    pub fn new() -> YamlStandardRepresenter {
+        YamlStandardRepresenter {
+            ctor: YamlStandardConstructor::new()
+        }
+    }
+
+    fn represent_plain(&self, emitter: &mut YamlEmitter, value: &str) -> Result<(), YamlError> {
+        emitter.emit_scalar_event(None, None, value, true, false, YamlScalarStyle::YamlPlainScalarStyle)
+    }
+
+    fn represent_string(&self, emitter: &mut YamlEmitter, tag: Option<&str>, value: &str) -> Result<(), YamlError> {
+        // quote the string if it would be resolved to another type as a plain scalar
+        let plain_implicit = match self.ctor.resolve_plain_scalar(value.to_string()) {
+            YamlStandardData::YamlString(ref s) => !s.is_empty(),
+            _ => false
+        };
+        let implicit = tag.is_none();
+
+        if plain_implicit {
+            emitter.emit_scalar_event(None, tag, value, implicit, implicit, YamlScalarStyle::YamlAnyScalarStyle)
+        } else {
+            emitter.emit_scalar_event(None, tag, value, false, implicit, YamlScalarStyle::YamlSingleQuotedScalarStyle)
+        }
+    }
+
+    // a node carries only one tag, so the data inside YamlTagged has to be one
+    // of the untagged types
+    fn represent_tagged(&self, emitter: &mut YamlEmitter, tag: &str, data: &YamlStandardData) -> Result<(), YamlError> {
+        let text = match *data.resolved() {
+            YamlStandardData::YamlString(ref s) => return self.represent_string(emitter, Some(tag), &s[..]),
+            YamlStandardData::YamlSequence(ref seq) => {
+                return emitter.emit_sequence(None, Some(tag), false, YamlSequenceStyle::YamlAnySequenceStyle, |e| {
+                    for item in seq.iter() {
+                        try!(self.represent(e, item));
+                    }
+                    Ok(())
+                });
+            },
+            YamlStandardData::YamlMapping(ref pairs) => {
+                return emitter.emit_mapping(None, Some(tag), false, YamlSequenceStyle::YamlAnySequenceStyle, |e| {
+                    for &(ref key, ref value) in pairs.iter() {
+                        try!(self.represent(e, key));
+                        try!(self.represent(e, value));
+                    }
+                    Ok(())
+                });
+            },
+            YamlStandardData::YamlInteger(i) => i.to_string(),
+            YamlStandardData::YamlUnsignedInteger(u) => u.to_string(),
+            YamlStandardData::YamlBigInteger(ref s) => s.clone(),
+            YamlStandardData::YamlFloat(f) => represent_float(f),
+            YamlStandardData::YamlNull => "null".to_string(),
+            YamlStandardData::YamlBool(b) => b.to_string(),
+            YamlStandardData::YamlTimestamp(ref t) => t.to_string(),
+            _ => return Err(YamlError::new(YamlErrorType::YAML_EMITTER_ERROR,
+                    Some(format!("cannot represent {:?} with the tag {}", data, tag))))
+        };
+
+        emitter.emit_scalar_event(None, Some(tag), &text[..], false, false, YamlScalarStyle::YamlPlainScalarStyle)
+    }
+
+    fn represent_binary(&self, emitter: &mut YamlEmitter, bytes: &[u8]) -> Result<(), YamlError> {
+        let encoded = codecs::encode_base64(bytes);
+        let mut value = String::new();
+        for line in encoded.as_bytes().chunks(76) {
+            value.push_str(str::from_utf8(line).unwrap());
+            value.push('\n');
+        }
+
+        emitter.emit_scalar_event(None, Some(BINARY_TAG), &value[..], false, false, YamlScalarStyle::YamlLiteralScalarStyle)
+    }
+
+    fn represent_pairs(&self, emitter: &mut YamlEmitter, tag: &str, pairs: &[(YamlStandardData, YamlStandardData)]) -> Result<(), YamlError> {
+        emitter.emit_sequence(None, Some(tag), false, YamlSequenceStyle::YamlAnySequenceStyle, |e| {
+            for &(ref key, ref value) in pairs.iter() {
+                try!(e.emit_mapping(None, None, true, YamlSequenceStyle::YamlAnySequenceStyle, |e| {
+                    try!(self.represent(e, key));
+                    self.represent(e, value)
+                }));
+            }
+            Ok(())
+        })
+    }
+
+    pub fn represent(&self, emitter: &mut YamlEmitter, data: &YamlStandardData) -> Result<(), YamlError> {
+        match *data {
+            YamlStandardData::YamlInteger(i) => self.represent_plain(emitter, &i.to_string()[..]),
+            YamlStandardData::YamlUnsignedInteger(u) => self.represent_plain(emitter, &u.to_string()[..]),
+            YamlStandardData::YamlBigInteger(ref s) => self.represent_plain(emitter, &s[..]),
+            YamlStandardData::YamlFloat(f) => self.represent_plain(emitter, &represent_float(f)[..]),
+            YamlStandardData::YamlString(ref s) => self.represent_string(emitter, None, &s[..]),
+            YamlStandardData::YamlNull => self.represent_plain(emitter, "null"),
+            YamlStandardData::YamlBool(b) => self.represent_plain(emitter, if b { "true" } else { "false" }),
+            YamlStandardData::YamlTimestamp(ref t) => self.represent_plain(emitter, &t.to_string()[..]),
+            YamlStandardData::YamlBinary(ref bytes) => self.represent_binary(emitter, &bytes[..]),
+            YamlStandardData::YamlSequence(ref seq) => {
+                emitter.emit_sequence(None, None, true, YamlSequenceStyle::YamlAnySequenceStyle, |e| {
+                    for item in seq.iter() {
+                        try!(self.represent(e, item));
+                    }
+                    Ok(())
+                })
+            },
+            YamlStandardData::YamlMapping(ref pairs) => {
+                emitter.emit_mapping(None, None, true, YamlSequenceStyle::YamlAnySequenceStyle, |e| {
+                    for &(ref key, ref value) in pairs.iter() {
+                        try!(self.represent(e, key));
+                        try!(self.represent(e, value));
+                    }
+                    Ok(())
+                })
+            },
+            YamlStandardData::YamlSet(ref keys) => {
+                emitter.emit_mapping(None, Some(SET_TAG), false, YamlSequenceStyle::YamlAnySequenceStyle, |e| {
+                    for key in keys.iter() {
+                        try!(self.represent(e, key));
+                        try!(self.represent_plain(e, "null"));
+                    }
+                    Ok(())
+                })
+            },
+            YamlStandardData::YamlOrderedMap(ref pairs) => self.represent_pairs(emitter, OMAP_TAG, &pairs[..]),
+            YamlStandardData::YamlPairs(ref pairs) => self.represent_pairs(emitter, PAIRS_TAG, &pairs[..]),
+            YamlStandardData::YamlTagged(ref tag, ref data) => self.represent_tagged(emitter, &tag[..], data),
+            YamlStandardData::YamlShared(ref data) => self.represent(emitter, data)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::YamlStandardRepresenter;
+    use constructor::YamlStandardData;
+    use constructor::YamlStandardData::*;
+    use constructor::{YamlTimestamp, YamlTime};
+    use emitter::YamlEmitter;
+    use ffi::YamlEncoding::YamlUtf8Encoding;
+
+    fn represent(data: &YamlStandardData) -> String {
+        let mut writer = Vec::new();
+        {
+            let repr = YamlStandardRepresenter::new();
+            let mut emitter = YamlEmitter::init(&mut writer);
+            emitter.emit_stream(YamlUtf8Encoding, |e| {
+                e.emit_document(None, &[], true, |e| repr.represent(e, data))
+            }).unwrap();
+        }
+        String::from_utf8(writer).unwrap()
+    }
+
+    #[test]
+    fn test_represent_scalars() {
+        let data = YamlSequence(vec![YamlInteger(-3), YamlFloat(1.0), YamlFloat(-0.5), YamlNull, YamlBool(true), YamlString("abc".to_string())]);
+        assert_eq!("- -3\n- 1.0\n- -0.5\n- null\n- true\n- abc\n", represent(&data));
+    }
+
+    #[test]
+    fn test_represent_ambiguous_strings() {
+        let data = YamlSequence(vec![YamlString("true".to_string()), YamlString("0x10".to_string()), YamlString("~".to_string()),
+            YamlString("".to_string()), YamlString("2002-12-14".to_string())]);
+        assert_eq!("- 'true'\n- '0x10'\n- '~'\n- ''\n- '2002-12-14'\n", represent(&data));
+    }
+
+    #[test]
+    fn test_represent_mapping() {
+        let data = YamlMapping(vec![
+            (YamlString("a".to_string()), YamlSequence(vec![YamlInteger(1)])),
+            (YamlString("b".to_string()), YamlMapping(vec![])),
+        ]);
+        assert_eq!("a:\n- 1\nb: {}\n", represent(&data));
+    }
+
+    #[test]
+    fn test_represent_timestamp() {
+        let data = YamlSequence(vec![
+            YamlTimestamp(YamlTimestamp { year: 2002, month: 12, day: 14, time: None }),
+            YamlTimestamp(YamlTimestamp { year: 2001, month: 12, day: 15,
+                time: Some(YamlTime { hour: 2, minute: 59, second: 43, nanosecond: 0, offset: Some(0) }) }),
+        ]);
+        assert_eq!("- 2002-12-14\n- 2001-12-15T02:59:43Z\n", represent(&data));
+    }
+
+    #[test]
+    fn test_represent_binary() {
+        let data = YamlMapping(vec![(YamlString("key".to_string()), YamlBinary(vec![0u8; 60]))]);
+        let expected = "key: !!binary |\n  AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\n  AAAA\n";
+        assert_eq!(expected, represent(&data));
+
+        assert_eq!(Ok(vec![data]), ::parse_bytes_utf8(expected.as_bytes()));
+    }
+
+    #[test]
+    fn test_represent_typed_collections() {
+        let a = YamlString("a".to_string());
+        let b = YamlString("b".to_string());
+        let data = YamlSequence(vec![
+            YamlSet(vec![a.clone(), b.clone()]),
+            YamlOrderedMap(vec![(b.clone(), YamlInteger(1)), (a.clone(), YamlInteger(2))]),
+            YamlPairs(vec![(a.clone(), YamlInteger(1)), (a.clone(), YamlInteger(2))]),
+        ]);
+        let expected = "- !!set\n  a: null\n  b: null\n- !!omap\n  - b: 1\n  - a: 2\n- !!pairs\n  - a: 1\n  - a: 2\n";
+        assert_eq!(expected, represent(&data));
+
+        assert_eq!(Ok(vec![data]), ::parse_bytes_utf8(expected.as_bytes()));
+    }
+
+    #[test]
+    fn test_represent_tagged() {
+        let tagged = |tag: &str, data| YamlTagged(tag.to_string(), Box::new(data));
+        let data = YamlSequence(vec![
+            tagged("!Ref", YamlString("foo".to_string())),
+            tagged("!env", YamlString("5".to_string())),
+            tagged("!env", YamlInteger(5)),
+            tagged("!Sub", YamlSequence(vec![YamlString("a".to_string())])),
+            tagged("tag:example.com,2000:point", YamlMapping(vec![(YamlString("x".to_string()), YamlInteger(1))])),
+        ]);
+        let expected = "- !Ref foo
+- !env '5'
+- !env 5
+- !Sub
+  - a
+- !<tag:example.com,2000:point>
+  x: 1
+";
+        assert_eq!(expected, represent(&data));
+
+        assert_eq!(Ok(vec![data]), ::parse_bytes_utf8(expected.as_bytes()));
+    }
+}
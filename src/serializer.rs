@@ -0,0 +1,353 @@
+use serde::ser;
+use serde::ser::Serialize;
+
+use constructor::YamlStandardData;
+use emitter::{YamlEmitter, YamlEmitterOptions};
+use error::YamlError;
+use ffi;
+use ffi::{YamlErrorType, YamlScalarStyle, YamlSequenceStyle};
+use representer::YamlStandardRepresenter;
+
+use std::fmt;
+use std::io::Write;
+
+impl ser::Error for YamlError {
+    fn custom<T: fmt::Display>(msg: T) -> YamlError {
+        YamlError::new(YamlErrorType::YAML_EMITTER_ERROR, Some(msg.to_string()))
+    }
+}
+
+pub struct YamlSerializer<'r> {
+    emitter: Box<YamlEmitter<'r>>,
+    repr: YamlStandardRepresenter,
+    stream_started: bool
+}
+
+impl<'r> YamlSerializer<'r> {
+    pub fn new<'a>(writer: &'a mut Write) -> YamlSerializer<'a> {
+        YamlSerializer::with_options(writer, &Default::default())
+    }
+
+    pub fn with_options<'a>(writer: &'a mut Write, options: &YamlEmitterOptions) -> YamlSerializer<'a> {
+        YamlSerializer {
+            emitter: YamlEmitter::init_with_options(writer, options),
+            repr: YamlStandardRepresenter::new(),
+            stream_started: false
+        }
+    }
+
+    pub fn serialize_document<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), YamlError> {
+        if !self.stream_started {
+            try!(self.emitter.emit_stream_start_event(ffi::YamlEncoding::YamlUtf8Encoding));
+            self.stream_started = true;
+        }
+
+        try!(self.emitter.emit_document_start_event(None, &[], true));
+        try!(value.serialize(&mut *self));
+        self.emitter.emit_document_end_event(true)
+    }
+
+    pub fn finish(&mut self) -> Result<(), YamlError> {
+        if !self.stream_started {
+            try!(self.emitter.emit_stream_start_event(ffi::YamlEncoding::YamlUtf8Encoding));
+            self.stream_started = true;
+        }
+
+        try!(self.emitter.emit_stream_end_event());
+        self.emitter.flush()
+    }
+
+    fn emit_plain(&mut self, value: &str) -> Result<(), YamlError> {
+        self.emitter.emit_scalar_event(None, None, value, true, false, YamlScalarStyle::YamlPlainScalarStyle)
+    }
+
+    fn emit_data(&mut self, data: &YamlStandardData) -> Result<(), YamlError> {
+        self.repr.represent(&mut self.emitter, data)
+    }
+
+    fn start_sequence(&mut self) -> Result<(), YamlError> {
+        self.emitter.emit_sequence_start_event(None, None, true, YamlSequenceStyle::YamlAnySequenceStyle)
+    }
+
+    fn start_mapping(&mut self) -> Result<(), YamlError> {
+        self.emitter.emit_mapping_start_event(None, None, true, YamlSequenceStyle::YamlAnySequenceStyle)
+    }
+
+    // enum variants with content are written as a single-key mapping
+    fn start_variant(&mut self, variant: &str) -> Result<(), YamlError> {
+        try!(self.start_mapping());
+        self.emit_data(&YamlStandardData::YamlString(variant.to_string()))
+    }
+}
+
+pub fn to_writer<T: Serialize + ?Sized>(writer: &mut Write, value: &T) -> Result<(), YamlError> {
+    let mut ser = YamlSerializer::new(writer);
+    try!(ser.serialize_document(value));
+    ser.finish()
+}
+
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, YamlError> {
+    let mut buf = Vec::new();
+    try!(to_writer(&mut buf, value));
+    Ok(String::from_utf8(buf).unwrap())
+}
+
+pub struct YamlCompound<'a, 'r: 'a> {
+    ser: &'a mut YamlSerializer<'r>,
+    variant: bool
+}
+
+impl<'a, 'r> YamlCompound<'a, 'r> {
+    fn end_sequence(self) -> Result<(), YamlError> {
+        try!(self.ser.emitter.emit_sequence_end_event());
+        if self.variant {
+            try!(self.ser.emitter.emit_mapping_end_event());
+        }
+        Ok(())
+    }
+
+    fn end_mapping(self) -> Result<(), YamlError> {
+        try!(self.ser.emitter.emit_mapping_end_event());
+        if self.variant {
+            try!(self.ser.emitter.emit_mapping_end_event());
+        }
+        Ok(())
+    }
+}
+
+impl<'a, 'r> ser::Serializer for &'a mut YamlSerializer<'r> {
+    type Ok = ();
+    type Error = YamlError;
+
+    type SerializeSeq = YamlCompound<'a, 'r>;
+    type SerializeTuple = YamlCompound<'a, 'r>;
+    type SerializeTupleStruct = YamlCompound<'a, 'r>;
+    type SerializeTupleVariant = YamlCompound<'a, 'r>;
+    type SerializeMap = YamlCompound<'a, 'r>;
+    type SerializeStruct = YamlCompound<'a, 'r>;
+    type SerializeStructVariant = YamlCompound<'a, 'r>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), YamlError> {
+        self.emit_data(&YamlStandardData::YamlBool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), YamlError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), YamlError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), YamlError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), YamlError> {
+        self.emit_plain(&v.to_string()[..])
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), YamlError> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), YamlError> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), YamlError> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), YamlError> {
+        self.emit_plain(&v.to_string()[..])
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), YamlError> {
+        if v.is_finite() {
+            self.emit_plain(&format!("{:?}", v)[..])
+        } else {
+            self.serialize_f64(v as f64)
+        }
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), YamlError> {
+        self.emit_data(&YamlStandardData::YamlFloat(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), YamlError> {
+        self.emit_data(&YamlStandardData::YamlString(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), YamlError> {
+        self.emit_data(&YamlStandardData::YamlString(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), YamlError> {
+        self.emit_data(&YamlStandardData::YamlBinary(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<(), YamlError> {
+        self.emit_data(&YamlStandardData::YamlNull)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), YamlError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), YamlError> {
+        self.emit_data(&YamlStandardData::YamlNull)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), YamlError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str) -> Result<(), YamlError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<(), YamlError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _variant_index: u32,
+            variant: &'static str, value: &T) -> Result<(), YamlError> {
+        try!(self.start_variant(variant));
+        try!(value.serialize(&mut *self));
+        self.emitter.emit_mapping_end_event()
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<YamlCompound<'a, 'r>, YamlError> {
+        try!(self.start_sequence());
+        Ok(YamlCompound { ser: self, variant: false })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<YamlCompound<'a, 'r>, YamlError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<YamlCompound<'a, 'r>, YamlError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _variant_index: u32,
+            variant: &'static str, _len: usize) -> Result<YamlCompound<'a, 'r>, YamlError> {
+        try!(self.start_variant(variant));
+        try!(self.start_sequence());
+        Ok(YamlCompound { ser: self, variant: true })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<YamlCompound<'a, 'r>, YamlError> {
+        try!(self.start_mapping());
+        Ok(YamlCompound { ser: self, variant: false })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<YamlCompound<'a, 'r>, YamlError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _variant_index: u32,
+            variant: &'static str, _len: usize) -> Result<YamlCompound<'a, 'r>, YamlError> {
+        try!(self.start_variant(variant));
+        try!(self.start_mapping());
+        Ok(YamlCompound { ser: self, variant: true })
+    }
+}
+
+impl<'a, 'r> ser::SerializeSeq for YamlCompound<'a, 'r> {
+    type Ok = ();
+    type Error = YamlError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), YamlError> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), YamlError> {
+        self.end_sequence()
+    }
+}
+
+impl<'a, 'r> ser::SerializeTuple for YamlCompound<'a, 'r> {
+    type Ok = ();
+    type Error = YamlError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), YamlError> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), YamlError> {
+        self.end_sequence()
+    }
+}
+
+impl<'a, 'r> ser::SerializeTupleStruct for YamlCompound<'a, 'r> {
+    type Ok = ();
+    type Error = YamlError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), YamlError> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), YamlError> {
+        self.end_sequence()
+    }
+}
+
+impl<'a, 'r> ser::SerializeTupleVariant for YamlCompound<'a, 'r> {
+    type Ok = ();
+    type Error = YamlError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), YamlError> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), YamlError> {
+        self.end_sequence()
+    }
+}
+
+impl<'a, 'r> ser::SerializeMap for YamlCompound<'a, 'r> {
+    type Ok = ();
+    type Error = YamlError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), YamlError> {
+        key.serialize(&mut *self.ser)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), YamlError> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), YamlError> {
+        self.end_mapping()
+    }
+}
+
+impl<'a, 'r> ser::SerializeStruct for YamlCompound<'a, 'r> {
+    type Ok = ();
+    type Error = YamlError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), YamlError> {
+        try!(self.ser.emit_data(&YamlStandardData::YamlString(key.to_string())));
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), YamlError> {
+        self.end_mapping()
+    }
+}
+
+impl<'a, 'r> ser::SerializeStructVariant for YamlCompound<'a, 'r> {
+    type Ok = ();
+    type Error = YamlError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), YamlError> {
+        try!(self.ser.emit_data(&YamlStandardData::YamlString(key.to_string())));
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), YamlError> {
+        self.end_mapping()
+    }
+}
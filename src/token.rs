@@ -0,0 +1,110 @@
+use ffi;
+use ffi::{YamlEncoding, YamlScalarStyle};
+use ffi::yaml_token_type_t::*;
+use std::mem;
+
+use codecs;
+use event::{YamlVersionDirective, YamlTagDirective};
+use ::error::YamlMark;
+
+#[derive(Debug, PartialEq)]
+pub enum YamlTokenSpec {
+    YamlNoToken,
+    YamlStreamStartToken(YamlEncoding),
+    YamlStreamEndToken,
+    YamlVersionDirectiveToken(YamlVersionDirective),
+    YamlTagDirectiveToken(YamlTagDirective),
+    YamlDocumentStartToken,
+    YamlDocumentEndToken,
+    YamlBlockSequenceStartToken,
+    YamlBlockMappingStartToken,
+    YamlBlockEndToken,
+    YamlFlowSequenceStartToken,
+    YamlFlowSequenceEndToken,
+    YamlFlowMappingStartToken,
+    YamlFlowMappingEndToken,
+    YamlBlockEntryToken,
+    YamlFlowEntryToken,
+    YamlKeyToken,
+    YamlValueToken,
+    YamlAliasToken(String),
+    YamlAnchorToken(String),
+    YamlTagToken(String, String),
+    YamlScalarToken(String, YamlScalarStyle),
+}
+
+#[derive(Debug)]
+pub struct YamlToken {
+    pub spec: YamlTokenSpec,
+    pub start: YamlMark,
+    pub end: YamlMark
+}
+
+impl YamlToken {
+    pub unsafe fn load(token: &ffi::yaml_token_t) -> YamlToken {
+        YamlToken {
+            spec: YamlToken::load_spec(token),
+            start: YamlMark::conv(&token.start_mark),
+            end: YamlMark::conv(&token.end_mark)
+        }
+    }
+
+    unsafe fn load_spec(token: &ffi::yaml_token_t) -> YamlTokenSpec {
+        match token.token_type {
+            YAML_NO_TOKEN => YamlTokenSpec::YamlNoToken,
+            YAML_STREAM_START_TOKEN => {
+                let tok_data: &ffi::yaml_stream_start_token_t = mem::transmute(&token.data);
+                YamlTokenSpec::YamlStreamStartToken(tok_data.encoding)
+            },
+            YAML_STREAM_END_TOKEN => YamlTokenSpec::YamlStreamEndToken,
+            YAML_VERSION_DIRECTIVE_TOKEN => {
+                let tok_data: &ffi::yaml_version_directive_t = mem::transmute(&token.data);
+                YamlTokenSpec::YamlVersionDirectiveToken(YamlVersionDirective {
+                    major: tok_data.major as isize,
+                    minor: tok_data.minor as isize
+                })
+            },
+            YAML_TAG_DIRECTIVE_TOKEN => {
+                let tok_data: &ffi::yaml_tag_directive_token_t = mem::transmute(&token.data);
+                YamlTokenSpec::YamlTagDirectiveToken(YamlTagDirective {
+                    handle: codecs::decode_c_str(tok_data.handle).unwrap(),
+                    prefix: codecs::decode_c_str(tok_data.prefix).unwrap()
+                })
+            },
+            YAML_DOCUMENT_START_TOKEN => YamlTokenSpec::YamlDocumentStartToken,
+            YAML_DOCUMENT_END_TOKEN => YamlTokenSpec::YamlDocumentEndToken,
+            YAML_BLOCK_SEQUENCE_START_TOKEN => YamlTokenSpec::YamlBlockSequenceStartToken,
+            YAML_BLOCK_MAPPING_START_TOKEN => YamlTokenSpec::YamlBlockMappingStartToken,
+            YAML_BLOCK_END_TOKEN => YamlTokenSpec::YamlBlockEndToken,
+            YAML_FLOW_SEQUENCE_START_TOKEN => YamlTokenSpec::YamlFlowSequenceStartToken,
+            YAML_FLOW_SEQUENCE_END_TOKEN => YamlTokenSpec::YamlFlowSequenceEndToken,
+            YAML_FLOW_MAPPING_START_TOKEN => YamlTokenSpec::YamlFlowMappingStartToken,
+            YAML_FLOW_MAPPING_END_TOKEN => YamlTokenSpec::YamlFlowMappingEndToken,
+            YAML_BLOCK_ENTRY_TOKEN => YamlTokenSpec::YamlBlockEntryToken,
+            YAML_FLOW_ENTRY_TOKEN => YamlTokenSpec::YamlFlowEntryToken,
+            YAML_KEY_TOKEN => YamlTokenSpec::YamlKeyToken,
+            YAML_VALUE_TOKEN => YamlTokenSpec::YamlValueToken,
+            YAML_ALIAS_TOKEN => {
+                let tok_data: &ffi::yaml_alias_token_t = mem::transmute(&token.data);
+                YamlTokenSpec::YamlAliasToken(codecs::decode_c_str(tok_data.value).unwrap())
+            },
+            YAML_ANCHOR_TOKEN => {
+                let tok_data: &ffi::yaml_anchor_token_t = mem::transmute(&token.data);
+                YamlTokenSpec::YamlAnchorToken(codecs::decode_c_str(tok_data.value).unwrap())
+            },
+            YAML_TAG_TOKEN => {
+                let tok_data: &ffi::yaml_tag_token_t = mem::transmute(&token.data);
+                let handle = codecs::decode_c_str(tok_data.handle).unwrap();
+                let suffix = codecs::decode_c_str(tok_data.suffix).unwrap();
+
+                YamlTokenSpec::YamlTagToken(handle, suffix)
+            },
+            YAML_SCALAR_TOKEN => {
+                let tok_data: &ffi::yaml_scalar_token_t = mem::transmute(&token.data);
+                let value = codecs::decode_buf(tok_data.value, tok_data.length).unwrap();
+
+                YamlTokenSpec::YamlScalarToken(value, tok_data.style)
+            }
+        }
+    }
+}
@@ -0,0 +1,328 @@
+#![cfg(feature = "serde")]
+
+extern crate yaml;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+use yaml::deserializer;
+use yaml::serializer;
+use yaml::ffi::YamlErrorType;
+use yaml::parser::YamlLimits;
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::fmt;
+use serde::de::IgnoredAny;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Player {
+    name: String,
+    hr: u32,
+    avg: Option<f64>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Meters(f64);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Shape {
+    Empty,
+    Circle(f64),
+    Point(i32, i32),
+    Rect { w: u32, h: u32 },
+}
+
+// serde has no byte buffer type of its own, so this goes through
+// serialize_bytes and visit_byte_buf directly
+#[derive(Debug, PartialEq)]
+struct Bytes(Vec<u8>);
+
+impl serde::Serialize for Bytes {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0[..])
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Bytes {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
+        struct BytesVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for BytesVisitor {
+            type Value = Bytes;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("bytes")
+            }
+
+            fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Bytes, E> {
+                Ok(Bytes(v))
+            }
+        }
+
+        deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Borrowed<'a> {
+    key: &'a str,
+    list: Vec<&'a str>,
+}
+
+#[test]
+fn de_struct() {
+    let player: Player = deserializer::from_str("name: Mark McGwire\nhr: 65\navg: 0.278\n").unwrap();
+    assert_eq!(Player { name: "Mark McGwire".to_string(), hr: 65, avg: Some(0.278) }, player);
+}
+
+#[test]
+fn de_option_and_unknown_fields() {
+    let players: Vec<Player> = deserializer::from_str("
+- name: Sammy Sosa
+  hr: 63
+  avg: ~
+  team: {name: Cubs, league: [national]}
+- name: Ken Griffey
+  hr: 0
+  avg:
+").unwrap();
+    assert_eq!(vec![
+        Player { name: "Sammy Sosa".to_string(), hr: 63, avg: None },
+        Player { name: "Ken Griffey".to_string(), hr: 0, avg: None },
+    ], players);
+}
+
+#[test]
+fn de_string_fields_keep_scalar_text() {
+    let map: BTreeMap<String, String> = deserializer::from_str("version: 1.10\nenabled: yes").unwrap();
+    assert_eq!(Some(&"1.10".to_string()), map.get("version"));
+    assert_eq!(Some(&"yes".to_string()), map.get("enabled"));
+}
+
+#[test]
+fn de_newtype() {
+    let m: Meters = deserializer::from_str("1.5").unwrap();
+    assert_eq!(Meters(1.5), m);
+}
+
+#[test]
+fn de_enum_single_key_map() {
+    let shapes: Vec<Shape> = deserializer::from_str("
+- Empty
+- Circle: 2.0
+- Point: [1, -2]
+- Rect: {w: 3, h: 4}
+").unwrap();
+    assert_eq!(vec![Shape::Empty, Shape::Circle(2.0), Shape::Point(1, -2), Shape::Rect { w: 3, h: 4 }], shapes);
+}
+
+#[test]
+fn de_enum_tagged() {
+    let shapes: Vec<Shape> = deserializer::from_str("
+- !Empty
+- !Circle 2.0
+- !Point [1, -2]
+- !Rect {w: 3, h: 4}
+").unwrap();
+    assert_eq!(vec![Shape::Empty, Shape::Circle(2.0), Shape::Point(1, -2), Shape::Rect { w: 3, h: 4 }], shapes);
+}
+
+#[test]
+fn de_borrowed_str() {
+    let data = "key: 'value'\nlist: [a, \"b\", caf\u{e9}]\n";
+    let borrowed: Borrowed = deserializer::from_str(data).unwrap();
+    assert_eq!(Borrowed { key: "value", list: vec!["a", "b", "caf\u{e9}"] }, borrowed);
+}
+
+#[test]
+fn de_escaped_str_cannot_be_borrowed() {
+    let res: Result<Borrowed, _> = deserializer::from_str("key: \"a\\tb\"\nlist: []\n");
+    assert!(res.is_err());
+}
+
+#[test]
+fn de_alias() {
+    let map: BTreeMap<String, Vec<u32>> = deserializer::from_str("a: &x [1, 2]\nb: *x\n").unwrap();
+    assert_eq!(Some(&vec![1, 2]), map.get("b"));
+}
+
+#[test]
+fn de_alias_keeps_redefined_anchor() {
+    let list: Vec<Vec<i32>> = deserializer::from_str("- &a [&b 1]\n- &b [2]\n- *a\n- *b\n").unwrap();
+    assert_eq!(vec![vec![1], vec![2], vec![1], vec![2]], list);
+}
+
+fn assert_limit_error<T: fmt::Debug>(res: Result<T, yaml::error::YamlError>, problem: &str) {
+    match res {
+        Ok(v) => panic!("unexpected result: {:?}", v),
+        Err(e) => {
+            assert!(e.limit_exceeded);
+            assert_eq!(Some(problem.to_string()), e.problem);
+        }
+    }
+}
+
+#[test]
+fn de_alias_expansion_limit() {
+    let laughs = "a: &a [x, x, x, x, x, x, x, x, x, x]\n\
+                  b: &b [*a, *a, *a, *a, *a, *a, *a, *a, *a, *a]\n\
+                  c: &c [*b, *b, *b, *b, *b, *b, *b, *b, *b, *b]\n\
+                  d: &d [*c, *c, *c, *c, *c, *c, *c, *c, *c, *c]\n\
+                  e: [*d, *d, *d, *d, *d, *d, *d, *d, *d, *d]\n";
+    let mut limits: YamlLimits = Default::default();
+    limits.max_nodes = Some(10000);
+    let res: Result<BTreeMap<String, IgnoredAny>, _> =
+        deserializer::from_str_with_limits(laughs, limits);
+    assert_limit_error(res, "exceeded the maximum number of nodes");
+
+    let mut limits: YamlLimits = Default::default();
+    limits.max_alias_dereferences = Some(20);
+    let res: Result<BTreeMap<String, IgnoredAny>, _> =
+        deserializer::from_str_with_limits(laughs, limits);
+    assert_limit_error(res, "exceeded the maximum number of alias dereferences");
+}
+
+#[test]
+fn de_depth_and_scalar_limits() {
+    let mut limits: YamlLimits = Default::default();
+    limits.max_depth = Some(2);
+    let res: Result<Vec<Vec<Vec<u32>>>, _> = deserializer::from_str_with_limits("[[[1]]]", limits);
+    assert_limit_error(res, "exceeded the maximum nesting depth");
+    let nested: Vec<Vec<u32>> = deserializer::from_str_with_limits("[[1]]", limits).unwrap();
+    assert_eq!(vec![vec![1]], nested);
+
+    let mut limits: YamlLimits = Default::default();
+    limits.max_scalar_length = Some(4);
+    let mut reader = Cursor::new("[abc, abcdef]".as_bytes());
+    let res: Result<Vec<String>, _> = deserializer::from_reader_with_limits(&mut reader, limits);
+    assert_limit_error(res, "exceeded the maximum scalar length");
+}
+
+#[test]
+fn de_document_size_limit() {
+    let mut limits: YamlLimits = Default::default();
+    limits.max_document_bytes = Some(12);
+    let list: Vec<String> = deserializer::from_str_with_limits("[\u{e9}\u{e9}\u{e9}, b]", limits).unwrap();
+    assert_eq!(vec!["\u{e9}\u{e9}\u{e9}".to_string(), "b".to_string()], list);
+    let res: Result<Vec<String>, _> = deserializer::from_str_with_limits("[\u{e9}\u{e9}\u{e9}, bcd]", limits);
+    assert_limit_error(res, "exceeded the maximum document size");
+
+    let mut reader = Cursor::new("[abc, def, ghi]".as_bytes());
+    let res: Result<Vec<String>, _> = deserializer::from_reader_with_limits(&mut reader, limits);
+    assert_limit_error(res, "exceeded the maximum document size");
+}
+
+#[test]
+fn de_explicit_tags_match_constructor() {
+    let list: Vec<Option<String>> = deserializer::from_str("- !!null\n- !!null ~\n- !!str 1\n").unwrap();
+    assert_eq!(vec![None, None, Some("1".to_string())], list);
+    let value: f64 = deserializer::from_str("!!float 1").unwrap();
+    assert_eq!(1.0, value);
+    let value: f64 = deserializer::from_str("!!float 99999999999999999999").unwrap();
+    assert_eq!(1e20, value);
+
+    for data in ["!!null foo", "!!int 1.5", "!!bool yes please"].iter() {
+        let res: Result<Option<String>, _> = deserializer::from_str(data);
+        let expected = yaml::parse_bytes_utf8(data.as_bytes()).unwrap_err();
+        assert_eq!(expected.problem, res.unwrap_err().problem);
+    }
+}
+
+#[test]
+fn de_reader() {
+    let mut reader = Cursor::new("[1, 2, 3]".as_bytes());
+    let list: Vec<i64> = deserializer::from_reader(&mut reader).unwrap();
+    assert_eq!(vec![1, 2, 3], list);
+}
+
+#[test]
+fn de_empty_stream() {
+    let value: Option<Player> = deserializer::from_str("").unwrap();
+    assert_eq!(None, value);
+}
+
+#[test]
+fn de_error_mark() {
+    let res: Result<Player, _> = deserializer::from_str("name: Mark McGwire\nhr: lots\n");
+    match res {
+        Ok(p) => panic!("unexpected result: {:?}", p),
+        Err(e) => {
+            assert_eq!(YamlErrorType::YAML_PARSER_ERROR, e.kind);
+            let mark = e.context.unwrap().problem_mark;
+            assert_eq!((1, 4), (mark.line, mark.column));
+        }
+    }
+}
+
+#[test]
+fn de_multiple_documents_error() {
+    let res: Result<u32, _> = deserializer::from_str("1\n--- 2\n");
+    assert!(res.is_err());
+}
+
+#[test]
+fn ser_struct() {
+    let player = Player { name: "Mark McGwire".to_string(), hr: 65, avg: Some(0.278) };
+    assert_eq!("name: Mark McGwire\nhr: 65\navg: 0.278\n", serializer::to_string(&player).unwrap());
+}
+
+#[test]
+fn ser_seq_of_structs() {
+    let players = vec![
+        Player { name: "Sammy Sosa".to_string(), hr: 63, avg: None },
+        Player { name: "true".to_string(), hr: 0, avg: Some(1.0) },
+    ];
+    assert_eq!("- name: Sammy Sosa\n  hr: 63\n  avg: null\n- name: 'true'\n  hr: 0\n  avg: 1.0\n",
+               serializer::to_string(&players).unwrap());
+}
+
+#[test]
+fn ser_enum_variants() {
+    let shapes = vec![Shape::Empty, Shape::Circle(2.0), Shape::Point(1, -2), Shape::Rect { w: 3, h: 4 }];
+    let text = serializer::to_string(&shapes).unwrap();
+    assert_eq!("- Empty\n- Circle: 2.0\n- Point:\n  - 1\n  - -2\n- Rect:\n    w: 3\n    h: 4\n", text);
+
+    let back: Vec<Shape> = deserializer::from_str(&text).unwrap();
+    assert_eq!(shapes, back);
+}
+
+#[test]
+fn ser_map_with_integer_keys() {
+    let mut map = BTreeMap::new();
+    map.insert(1u64, vec!["a".to_string()]);
+    map.insert(20u64, vec![]);
+    let text = serializer::to_string(&map).unwrap();
+    assert_eq!("1:\n- a\n20: []\n", text);
+
+    let back: BTreeMap<u64, Vec<String>> = deserializer::from_str(&text).unwrap();
+    assert_eq!(map, back);
+}
+
+#[test]
+fn ser_bytes_as_binary() {
+    let bytes = Bytes(b"GIF89a\x0c\x00".to_vec());
+    let text = serializer::to_string(&bytes).unwrap();
+    assert_eq!("!!binary |\n  R0lGODlhDAA=\n", text);
+
+    let back: Bytes = deserializer::from_str(&text).unwrap();
+    assert_eq!(bytes, back);
+}
+
+#[test]
+fn ser_to_writer() {
+    let mut writer = Vec::new();
+    serializer::to_writer(&mut writer, &(1u8, "~", -0.5f32)).unwrap();
+    assert_eq!("- 1\n- '~'\n- -0.5\n", String::from_utf8(writer).unwrap());
+}
+
+#[test]
+fn ser_multiple_documents() {
+    let mut writer = Vec::new();
+    {
+        let mut ser = serializer::YamlSerializer::new(&mut writer);
+        ser.serialize_document(&1u32).unwrap();
+        ser.serialize_document(&Shape::Circle(0.5)).unwrap();
+        ser.finish().unwrap();
+    }
+    assert_eq!("1\n---\nCircle: 0.5\n", String::from_utf8(writer).unwrap());
+}
@@ -2,7 +2,7 @@
 
 extern crate yaml;
 
-use yaml::constructor::YamlStandardData;
+use yaml::constructor::{YamlStandardData, YamlTimestamp};
 use yaml::ffi::YamlEncoding;
 use std::io::Cursor;
 
@@ -47,6 +47,12 @@ macro_rules! yfloat{
     )
 }
 
+macro_rules! ydate{
+    ($y: expr, $m: expr, $d: expr) => (
+        YamlStandardData::YamlTimestamp(YamlTimestamp { year: $y, month: $m, day: $d, time: None })
+    )
+}
+
 macro_rules! yseq{
     ($($e:expr),*) => (
         YamlStandardData::YamlSequence(vec![$(($e),)*])
@@ -134,8 +140,8 @@ fn alias() {
 fn complex_keys() {
     test_utf8!("source/complex_key.yml",
     y_cmp_map!{
-        yseq![ystr!("Detroit Tigers"), ystr!("Chicago Cubs")] => yseq![ystr!("2001-07-23")],
-        yseq![ystr!("New York Yankees"), ystr!("Atlanta Braves")] => yseq![ystr!("2001-07-02"), ystr!("2001-08-12"), ystr!("2001-08-14")]
+        yseq![ystr!("Detroit Tigers"), ystr!("Chicago Cubs")] => yseq![ydate!(2001, 7, 23)],
+        yseq![ystr!("New York Yankees"), ystr!("Atlanta Braves")] => yseq![ydate!(2001, 7, 2), ydate!(2001, 8, 12), ydate!(2001, 8, 14)]
     })
 }
 
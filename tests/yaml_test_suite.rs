@@ -0,0 +1,378 @@
+// Runs the cases under tests/yaml-test-suite, which were generated from the
+// yaml-test-suite project (https://github.com/yaml/yaml-test-suite); see the
+// ReadMe in that directory for the revision. Each case directory, named after
+// its test ID (with numbered subdirectories for files holding several tests),
+// contains:
+//
+//   ===         the name of the test
+//   in.yaml     the input stream
+//   test.event  the expected event stream, one event per line
+//   in.json     optional, the expected values of the documents
+//   error       present when parsing the input has to fail
+//
+// Constructed values are resolved with the core schema, and in.json with the
+// JSON schema.
+
+extern crate yaml;
+
+use yaml::codecs;
+use yaml::constructor::{YamlConstructor, YamlStandardConstructor, YamlStandardData, YamlSchema};
+use yaml::error::YamlError;
+use yaml::event::{YamlEvent, YamlEventSpec};
+use yaml::ffi::{YamlScalarStyle, YamlSequenceStyle};
+use yaml::ffi::YamlEncoding::YamlUtf8Encoding;
+use yaml::parser::{YamlParser, YamlByteParser};
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const TABS: &'static str = "libyaml does not accept tabs as separation space here";
+const BLOCK_SCALAR_TABS: &'static str = "libyaml rejects tabs at the start of block scalar content";
+const EMPTY_KEYS: &'static str = "libyaml does not accept empty implicit keys";
+const FLOW_KEYS: &'static str = "libyaml requires flow mapping keys and their ':' on one line";
+const FLOW_INDICATORS: &'static str = "libyaml rejects plain scalars starting with ':' in flow collections";
+const BARE_DOCUMENTS: &'static str = "libyaml needs '---' to start a document after '...'";
+
+// Cases that fail because of known libyaml bugs, or because the value can't
+// be compared with in.json. Everything else in the suite has to pass.
+const SKIPPED: &'static [(&'static str, &'static str)] = &[
+    // valid YAML which libyaml rejects
+    ("2JQS", EMPTY_KEYS),
+    ("2LFX", "libyaml rejects reserved directives"),
+    ("2SXE", "libyaml stops anchor names at ':'"),
+    ("4ABK", "libyaml does not accept flow mapping keys without values"),
+    ("4MUZ/00", FLOW_KEYS),
+    ("4MUZ/01", FLOW_KEYS),
+    ("4MUZ/02", FLOW_KEYS),
+    ("58MP", FLOW_INDICATORS),
+    ("5MUD", FLOW_KEYS),
+    ("5T43", FLOW_INDICATORS),
+    ("6BCT", TABS),
+    ("6CA3", TABS),
+    ("6LVF", "libyaml rejects reserved directives"),
+    ("6M2F", EMPTY_KEYS),
+    ("7Z25", BARE_DOCUMENTS),
+    ("8XYN", "libyaml only allows ASCII alphanumerics, '-' and '_' in anchors"),
+    ("96NN/00", BLOCK_SCALAR_TABS),
+    ("96NN/01", BLOCK_SCALAR_TABS),
+    ("9SA2", FLOW_KEYS),
+    ("A2M4", TABS),
+    ("BEC7", "libyaml rejects %YAML directives for versions other than 1.1 and 1.2"),
+    ("CFD4", EMPTY_KEYS),
+    ("DBG4", FLOW_INDICATORS),
+    ("DK3J", "libyaml ends an unindented block scalar at a line which looks like a comment"),
+    ("DK95/00", TABS),
+    ("DK95/03", TABS),
+    ("DK95/04", TABS),
+    ("FP8R", "libyaml does not accept unindented block scalars in a top-level node"),
+    ("FRK4", EMPTY_KEYS),
+    ("HM87/00", FLOW_INDICATORS),
+    ("HWV9", "libyaml does not accept '...' without a document"),
+    ("K3WX", FLOW_KEYS),
+    ("M2N8/00", EMPTY_KEYS),
+    ("M7A3", BARE_DOCUMENTS),
+    ("MUS6/05", "libyaml rejects reserved directives"),
+    ("MUS6/06", "libyaml rejects reserved directives"),
+    ("NHX8", EMPTY_KEYS),
+    ("NJ66", FLOW_KEYS),
+    ("NKF9", EMPTY_KEYS),
+    ("Q5MG", TABS),
+    ("QT73", "libyaml does not accept '...' without a document"),
+    ("R4YG", BLOCK_SCALAR_TABS),
+    ("S3PD", EMPTY_KEYS),
+    ("SM9W/01", EMPTY_KEYS),
+    ("UKK6/00", EMPTY_KEYS),
+    ("UT92", FLOW_KEYS),
+    ("VJP3/01", FLOW_KEYS),
+    ("W4TN", "libyaml reads a '%' at the start of a block scalar line as a directive"),
+    ("W5VH", "libyaml only allows ASCII alphanumerics, '-' and '_' in aliases"),
+    ("Y79Y/001", BLOCK_SCALAR_TABS),
+    ("Y79Y/010", TABS),
+
+    // valid YAML which libyaml parses into different events
+    ("652Z", "libyaml drops a leading '?' from plain scalars in flow collections"),
+    ("HM87/01", "libyaml drops a leading '?' from plain scalars in flow collections"),
+    ("JEF9/02", "libyaml drops the line break of a literal scalar with only spaces"),
+    ("L24T/01", "libyaml drops the last line break of a literal scalar ending in spaces"),
+    ("Y2GN", "libyaml stops anchor names at ':'"),
+
+    // invalid YAML which libyaml accepts
+    ("9C9N", "libyaml does not check the indentation of flow collections"),
+    ("9HCY", "libyaml does not require '...' before directives"),
+    ("9JBA", "libyaml does not require space before comments"),
+    ("CVW2", "libyaml does not require space before comments"),
+    ("DK95/01", "libyaml accepts tabs as indentation of quoted scalar lines"),
+    ("EB22", "libyaml does not require '...' before directives"),
+    ("G5U8", "libyaml accepts '-' as a plain scalar in flow collections"),
+    ("MUS6/00", "libyaml does not require space before comments"),
+    ("MUS6/01", "libyaml accepts repeated %YAML directives"),
+    ("QB6E", "libyaml does not check the indentation of quoted scalars"),
+    ("RHX7", "libyaml does not require '...' before directives"),
+    ("S98Z", "libyaml accepts leading empty lines more indented than the block scalar"),
+    ("SU5Z", "libyaml does not require space before comments"),
+    ("X4QW", "libyaml does not require space before comments"),
+    ("Y79Y/003", "libyaml accepts tabs as indentation in flow collections"),
+    ("YJV2", "libyaml accepts '-' as a plain scalar in flow collections"),
+
+    // in.json has null for a scalar with the non-specific tag '!', which the
+    // core schema resolves to a string
+    ("UKK6/02", "'!' makes an empty scalar a string"),
+];
+
+fn read_file(path: &Path) -> Vec<u8> {
+    let mut buf = Vec::new();
+    File::open(path).and_then(|mut f| f.read_to_end(&mut buf)).unwrap();
+    buf
+}
+
+fn escape(value: &str) -> String {
+    let mut buf = String::new();
+    for c in value.chars() {
+        match c {
+            '\\' => buf.push_str("\\\\"),
+            '\0' => buf.push_str("\\0"),
+            '\x08' => buf.push_str("\\b"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            '\t' => buf.push_str("\\t"),
+            _ => buf.push(c)
+        }
+    }
+    buf
+}
+
+fn node_properties(anchor: &Option<String>, tag: &Option<String>) -> String {
+    let mut buf = String::new();
+    if let Some(ref anchor) = *anchor {
+        buf.push_str(&format!(" &{}", anchor));
+    }
+    if let Some(ref tag) = *tag {
+        buf.push_str(&format!(" <{}>", tag));
+    }
+    buf
+}
+
+// the line format of test.event
+fn format_event(event: &YamlEvent) -> String {
+    match event.spec {
+        YamlEventSpec::YamlNoEvent => "".to_string(),
+        YamlEventSpec::YamlStreamStartEvent(_) => "+STR".to_string(),
+        YamlEventSpec::YamlStreamEndEvent => "-STR".to_string(),
+        YamlEventSpec::YamlDocumentStartEvent(_, _, implicit) =>
+            if implicit { "+DOC".to_string() } else { "+DOC ---".to_string() },
+        YamlEventSpec::YamlDocumentEndEvent(implicit) =>
+            if implicit { "-DOC".to_string() } else { "-DOC ...".to_string() },
+        YamlEventSpec::YamlAliasEvent(ref anchor) => format!("=ALI *{}", anchor),
+        YamlEventSpec::YamlScalarEvent(ref param) => {
+            let style = match param.style {
+                YamlScalarStyle::YamlSingleQuotedScalarStyle => '\'',
+                YamlScalarStyle::YamlDoubleQuotedScalarStyle => '"',
+                YamlScalarStyle::YamlLiteralScalarStyle => '|',
+                YamlScalarStyle::YamlFoldedScalarStyle => '>',
+                _ => ':'
+            };
+            format!("=VAL{} {}{}", node_properties(&param.anchor, &param.tag), style, escape(&param.value[..]))
+        },
+        YamlEventSpec::YamlSequenceStartEvent(ref param) => {
+            let flow = if param.style == YamlSequenceStyle::YamlFlowSequenceStyle { " []" } else { "" };
+            format!("+SEQ{}{}", flow, node_properties(&param.anchor, &param.tag))
+        },
+        YamlEventSpec::YamlSequenceEndEvent => "-SEQ".to_string(),
+        YamlEventSpec::YamlMappingStartEvent(ref param) => {
+            let flow = if param.style == YamlSequenceStyle::YamlFlowSequenceStyle { " {}" } else { "" };
+            format!("+MAP{}{}", flow, node_properties(&param.anchor, &param.tag))
+        },
+        YamlEventSpec::YamlMappingEndEvent => "-MAP".to_string(),
+    }
+}
+
+fn load_all(data: &[u8], schema: YamlSchema) -> Result<Vec<YamlStandardData>, YamlError> {
+    let parser = YamlByteParser::init(data, YamlUtf8Encoding);
+    let ctor = YamlStandardConstructor::with_schema(schema);
+
+    parser.load().map(|doc_res|
+        doc_res.and_then(|doc| ctor.construct(doc.root().unwrap()))
+    ).collect()
+}
+
+// in.json may hold several JSON texts one after another, one per document
+fn split_json(json: &str) -> Vec<String> {
+    let mut docs = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for c in json.chars() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else {
+            match c {
+                '"' => in_string = true,
+                '[' | '{' => depth += 1,
+                ']' | '}' => depth -= 1,
+                _ if c.is_whitespace() && depth == 0 => {
+                    if !current.is_empty() {
+                        docs.push(current);
+                        current = String::new();
+                    }
+                    continue;
+                },
+                _ => ()
+            }
+        }
+        current.push(c);
+    }
+    if !current.is_empty() {
+        docs.push(current);
+    }
+    docs
+}
+
+// Compares a constructed value with the value from in.json. JSON objects are
+// unordered, JSON numbers make no difference between integers and floats, and
+// !!binary values are written as their base64 text.
+fn json_eq(data: &YamlStandardData, json: &YamlStandardData) -> bool {
+    match (data, json) {
+        (&YamlStandardData::YamlInteger(i), &YamlStandardData::YamlFloat(f)) |
+        (&YamlStandardData::YamlFloat(f), &YamlStandardData::YamlInteger(i)) => i as f64 == f,
+        (&YamlStandardData::YamlBinary(ref bytes), &YamlStandardData::YamlString(ref text)) =>
+            codecs::decode_base64(&text[..]).map_or(false, |decoded| &decoded == bytes),
+        (&YamlStandardData::YamlSequence(ref items), &YamlStandardData::YamlSequence(ref json_items)) =>
+            items.len() == json_items.len() && items.iter().zip(json_items.iter()).all(|(a, b)| json_eq(a, b)),
+        (&YamlStandardData::YamlMapping(ref pairs), &YamlStandardData::YamlMapping(ref json_pairs)) =>
+            pairs.len() == json_pairs.len() && pairs.iter().all(|&(ref key, ref value)| {
+                json_pairs.iter().any(|&(ref json_key, ref json_value)| json_eq(key, json_key) && json_eq(value, json_value))
+            }),
+        _ => data == json
+    }
+}
+
+// drops what JSON can't express: application tags and the collection types
+// which are plain mappings and sequences in JSON
+fn to_json_value(data: YamlStandardData) -> YamlStandardData {
+    let pairs = |pairs: Vec<(YamlStandardData, YamlStandardData)>| {
+        pairs.into_iter().map(|(k, v)| (to_json_value(k), to_json_value(v))).collect()
+    };
+
+    match data {
+        YamlStandardData::YamlTagged(_, data) => to_json_value(*data),
+        YamlStandardData::YamlShared(data) => to_json_value(data.resolved().clone()),
+        YamlStandardData::YamlSequence(items) =>
+            YamlStandardData::YamlSequence(items.into_iter().map(to_json_value).collect()),
+        YamlStandardData::YamlMapping(items) => YamlStandardData::YamlMapping(pairs(items)),
+        YamlStandardData::YamlSet(keys) => YamlStandardData::YamlMapping(
+            keys.into_iter().map(|k| (to_json_value(k), YamlStandardData::YamlNull)).collect()),
+        YamlStandardData::YamlOrderedMap(items) | YamlStandardData::YamlPairs(items) =>
+            YamlStandardData::YamlSequence(pairs(items).into_iter()
+                .map(|pair| YamlStandardData::YamlMapping(vec![pair])).collect()),
+        other => other
+    }
+}
+
+fn run_case(dir: &Path) -> Result<(), String> {
+    let input = read_file(&dir.join("in.yaml"));
+    let expect_error = dir.join("error").exists();
+
+    let parser = YamlByteParser::init(&input[..], YamlUtf8Encoding);
+    let mut events = Vec::new();
+    let mut parse_error = None;
+    for res in parser.parse() {
+        match res {
+            Ok(event) => events.push(format_event(&event)),
+            Err(e) => {
+                parse_error = Some(e);
+                break;
+            }
+        }
+    }
+
+    // the events before an error depend on how far each parser gets
+    match (expect_error, parse_error) {
+        (true, None) => return Err("expected a parse error".to_string()),
+        (true, Some(_)) => return Ok(()),
+        (false, Some(e)) => return Err(format!("unexpected parse error: {:?}", e)),
+        (false, None) => ()
+    }
+
+    let expected_events = String::from_utf8(read_file(&dir.join("test.event"))).unwrap();
+    let expected: Vec<&str> = expected_events.lines().collect();
+    if events != expected {
+        return Err(format!("event stream mismatch\nexpected:\n{}\nactual:\n{}", expected.join("\n"), events.join("\n")));
+    }
+
+    let docs = try!(load_all(&input[..], YamlSchema::YamlCoreSchema).map_err(|e| format!("construction failed: {:?}", e)));
+
+    let json_path = dir.join("in.json");
+    if json_path.exists() {
+        let json_text = String::from_utf8(read_file(&json_path)).unwrap();
+        let mut json = Vec::new();
+        for text in split_json(&json_text[..]).iter() {
+            let mut values = try!(load_all(text.as_bytes(), YamlSchema::YamlJsonSchema)
+                .map_err(|e| format!("in.json could not be loaded: {:?}", e)));
+            json.append(&mut values);
+        }
+
+        let docs: Vec<YamlStandardData> = docs.into_iter().map(to_json_value).collect();
+        if docs.len() != json.len() || !docs.iter().zip(json.iter()).all(|(a, b)| json_eq(a, b)) {
+            return Err(format!("value mismatch\nexpected: {:?}\nactual: {:?}", json, docs));
+        }
+    }
+
+    Ok(())
+}
+
+fn find_cases(dir: &Path, cases: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.join("in.yaml").exists() {
+            cases.push(path);
+        } else if path.is_dir() {
+            find_cases(&path, cases);
+        }
+    }
+}
+
+fn case_id(root: &Path, case: &Path) -> String {
+    let parts: Vec<String> = case.strip_prefix(root).unwrap().iter()
+        .map(|part| part.to_string_lossy().into_owned()).collect();
+    parts.join("/")
+}
+
+#[test]
+fn yaml_test_suite() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("yaml-test-suite");
+    let mut cases = Vec::new();
+    find_cases(&root, &mut cases);
+    cases.sort();
+    assert!(!cases.is_empty());
+
+    let mut failures = Vec::new();
+    let mut skipped = Vec::new();
+    for case in cases.iter() {
+        let id = case_id(&root, case);
+        if SKIPPED.iter().any(|&(skip, _)| skip == &id[..]) {
+            skipped.push(id);
+            continue;
+        }
+        if let Err(msg) = run_case(case) {
+            failures.push(format!("{}: {}", id, msg));
+        }
+    }
+
+    // keep the list from going stale
+    for &(id, _) in SKIPPED.iter() {
+        assert!(skipped.iter().any(|skip| &skip[..] == id), "skipped case {} does not exist", id);
+    }
+
+    if !failures.is_empty() {
+        panic!("{} of {} cases failed\n\n{}", failures.len(), cases.len(), failures.join("\n\n"));
+    }
+}